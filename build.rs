@@ -0,0 +1,138 @@
+// Check every shader under src/ with naga's GLSL frontend, so a broken shader fails the
+// build instead of the first run.
+//
+// The stage comes from the extension (.vert, .frag, .comp) or, for .glsl files, from the
+// file name (vertex.glsl, blur.frag.glsl). Files whose stage cannot be told, such as
+// shared #include files, are not checked on their own.
+//
+// naga targets Vulkan GLSL 440+, so only its syntax errors fail the build. What it rejects
+// for being outside that dialect is ignored: older #versions, uniforms outside a block,
+// combined samplers and GL-only built-ins like gl_VertexID. Defines are only known at run
+// time, so #ifdef branches are checked as if nothing was defined.
+
+use naga::front::glsl::{ErrorKind, Frontend, Options};
+use naga::ShaderStage;
+use std::path::{Path, PathBuf};
+
+// naga only has separate textures and samplers. Declaring the combined types as textures
+// keeps it parsing past their uniforms
+const COMBINED_SAMPLERS: &[(&str, &str)] = &[
+    ("sampler1D", "texture1D"),
+    ("sampler2D", "texture2D"),
+    ("sampler3D", "texture3D"),
+    ("samplerCube", "textureCube"),
+    ("sampler1DArray", "texture1DArray"),
+    ("sampler2DArray", "texture2DArray"),
+    ("samplerCubeArray", "textureCubeArray"),
+    ("sampler2DMS", "texture2DMS"),
+    ("sampler2DMSArray", "texture2DMSArray"),
+    ("sampler2DShadow", "texture2D"),
+    ("samplerCubeShadow", "textureCube"),
+    ("sampler2DArrayShadow", "texture2DArray"),
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=src");
+
+    let mut shaders = Vec::new();
+    find_shaders(Path::new("src"), &mut shaders);
+    shaders.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut options = Options::from(ShaderStage::Vertex);
+    for prefix in ["", "i", "u"] {
+        for (combined, texture) in COMBINED_SAMPLERS {
+            options
+                .defines
+                .insert(format!("{prefix}{combined}"), format!("{prefix}{texture}"));
+        }
+    }
+
+    let mut frontend = Frontend::default();
+    let mut failed = false;
+    for (path, stage) in &shaders {
+        println!("cargo:rerun-if-changed={}", path.display());
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{}: {error}", path.display());
+                failed = true;
+                continue;
+            }
+        };
+
+        // #include is resolved at run time by ShaderSource, blank it out and keep the lines
+        let parsed: String = source
+            .lines()
+            .map(|line| match line.trim_start().starts_with("#include") {
+                true => "",
+                false => line,
+            })
+            .flat_map(|line| [line, "\n"])
+            .collect();
+
+        options.stage = *stage;
+        let Err(errors) = frontend.parse(&options, &parsed) else {
+            continue;
+        };
+        for error in &errors.errors {
+            if !matches!(
+                error.kind,
+                ErrorKind::EndOfFile | ErrorKind::InvalidToken(..) | ErrorKind::PreprocessorError(_)
+            ) {
+                continue;
+            }
+            let location = error.meta.location(&parsed);
+            let line = source
+                .lines()
+                .nth(location.line_number as usize - 1)
+                .unwrap_or("");
+            eprintln!(
+                "{}:{}:{}: {}",
+                path.display(),
+                location.line_number,
+                location.line_position,
+                error.kind
+            );
+            eprintln!("{:>5} | {line}", location.line_number);
+            eprintln!(
+                "      | {}^",
+                " ".repeat(location.line_position.max(1) as usize - 1)
+            );
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn find_shaders(directory: &Path, shaders: &mut Vec<(PathBuf, ShaderStage)>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_shaders(&path, shaders);
+        } else if let Some(stage) = shader_stage(&path) {
+            shaders.push((path, stage));
+        }
+    }
+}
+
+fn shader_stage(path: &Path) -> Option<ShaderStage> {
+    let stage = |name: &str| match name {
+        "vert" | "vertex" => Some(ShaderStage::Vertex),
+        "frag" | "fragment" => Some(ShaderStage::Fragment),
+        "comp" | "compute" => Some(ShaderStage::Compute),
+        _ => None,
+    };
+    let extension = path.extension()?.to_str()?;
+    if extension != "glsl" {
+        return stage(extension);
+    }
+    // vertex.glsl, or blur.frag.glsl
+    let stem = Path::new(path.file_stem()?);
+    stage(stem.extension().unwrap_or(stem.as_os_str()).to_str()?)
+}
@@ -0,0 +1,420 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, token, Data, DeriveInput, Expr, Field, Fields, Lit, Type};
+
+/// Implements `opengl::Std140` for a struct with named fields.
+///
+/// Every field has to implement `Std140` itself or be an array of such types. `[f32; 2..=4]`
+/// and the `i32`/`u32` equivalents are vectors, any other array follows the std140 array
+/// rules, which also makes `[[f32; 4]; 4]` a column major `mat4`.
+#[proc_macro_derive(Std140)]
+pub fn derive_std140(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match std140(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Implements `opengl::Std430` for a struct with named fields, with the same field rules as
+/// `Std140`.
+#[proc_macro_derive(Std430)]
+pub fn derive_std430(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match std430(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+/// Implements `opengl::Vertex` for a `#[repr(C)]` struct with named fields.
+///
+/// Fields get locations in declaration order unless given `#[vertex(location = N)]`, two
+/// fields with the same location are an error.
+/// Integer fields are read as `int`/`uint` in the shader, mark them `#[vertex(normalized)]`
+/// to read them as floats scaled to 0..1 or -1..1 instead. Float fields cannot be marked.
+#[proc_macro_derive(Vertex, attributes(vertex))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match vertex(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn vertex(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut is_repr_c = false;
+    for attribute in input
+        .attrs
+        .iter()
+        .filter(|attribute| attribute.path().is_ident("repr"))
+    {
+        attribute.parse_nested_meta(|meta| {
+            is_repr_c |= meta.path.is_ident("C");
+            Ok(())
+        })?;
+    }
+    if !is_repr_c {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Vertex needs #[repr(C)] so the field offsets are stable",
+        ));
+    }
+
+    let fields = named_fields(input, "Vertex")?;
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut attributes = Vec::new();
+    let mut locations = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let mut location = index as u32;
+        let mut normalized = false;
+        for attribute in field
+            .attrs
+            .iter()
+            .filter(|attribute| attribute.path().is_ident("vertex"))
+        {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("location") {
+                    let value: syn::LitInt = meta.value()?.parse()?;
+                    location = value.base10_parse()?;
+                    Ok(())
+                } else if meta.path.is_ident("normalized") {
+                    if is_float(&field.ty) {
+                        return Err(meta.error(
+                            "`normalized` only applies to integer fields, floats are read as is",
+                        ));
+                    }
+                    normalized = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `location = N` or `normalized`"))
+                }
+            })?;
+        }
+
+        let ident = field.ident.as_ref().unwrap();
+        if let Some((_, other)) = locations.iter().find(|(used, _)| *used == location) {
+            return Err(syn::Error::new_spanned(
+                ident,
+                format!("location {location} is already used by `{other}`"),
+            ));
+        }
+        locations.push((location, ident));
+
+        let ty = &field.ty;
+        attributes.push(quote! {
+            ::opengl::VertexAttribute::new::<#ty>(
+                #location,
+                #normalized,
+                ::std::mem::offset_of!(#name #type_generics, #ident),
+            )
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::opengl::Vertex for #name #type_generics #where_clause {
+            fn attributes() -> ::std::vec::Vec<::opengl::VertexAttribute> {
+                ::std::vec![#(#attributes),*]
+            }
+        }
+    })
+}
+
+// `f32` or an array of them, what `normalized` would be ignored on
+fn is_float(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.is_ident("f32"),
+        Type::Array(array) => is_float(&array.elem),
+        _ => false,
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Layout {
+    Std140,
+    Std430,
+}
+
+impl Layout {
+    fn name(self) -> &'static str {
+        match self {
+            Layout::Std140 => "Std140",
+            Layout::Std430 => "Std430",
+        }
+    }
+
+    fn trait_path(self) -> TokenStream2 {
+        match self {
+            Layout::Std140 => quote!(::opengl::Std140),
+            Layout::Std430 => quote!(::opengl::Std430),
+        }
+    }
+
+    fn write_fn(self) -> TokenStream2 {
+        match self {
+            Layout::Std140 => quote!(write_std140),
+            Layout::Std430 => quote!(write_std430),
+        }
+    }
+}
+
+fn named_fields<'a>(
+    input: &'a DeriveInput,
+    derive: &str,
+) -> syn::Result<&'a Punctuated<Field, token::Comma>> {
+    match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => Ok(&fields.named),
+            _ => Err(syn::Error::new_spanned(
+                input,
+                format!("{derive} needs a struct with named fields"),
+            )),
+        },
+        _ => Err(syn::Error::new_spanned(
+            input,
+            format!("{derive} can only be derived for structs"),
+        )),
+    }
+}
+
+fn std140(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let layout = Layout::Std140;
+    let fields = named_fields(input, layout.name())?;
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut size = quote!(0usize);
+    let mut writes = Vec::new();
+    let mut members = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let field_name = ident.to_string();
+        let align = align(&field.ty, layout);
+        let field_size = self::size(&field.ty, layout);
+
+        // The offset of each field is the end of the previous one rounded up to its alignment
+        let offset = quote!(::opengl::std140::align_to(#size, #align));
+        let write = write(
+            &field.ty,
+            layout,
+            quote!(&self.#ident),
+            quote!(&mut out[offset..]),
+        );
+        writes.push(quote! {
+            {
+                let offset = #offset;
+                #write
+            }
+        });
+        let member = self::members(&field.ty);
+        members.push(quote! {
+            {
+                let offset = offset + #offset;
+                let name = match name.is_empty() {
+                    true => #field_name.to_string(),
+                    false => format!("{}.{}", name, #field_name),
+                };
+                #member
+            }
+        });
+        size = quote!(#offset + #field_size);
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::opengl::Std140 for #name #type_generics #where_clause {
+            // Structs are aligned like a vec4, and padded to a multiple of it
+            const ALIGN: usize = 16;
+            const SIZE: usize = ::opengl::std140::align_to(#size, 16);
+
+            fn write_std140(&self, out: &mut [u8]) {
+                #(#writes)*
+            }
+
+            fn std140_members(
+                name: &str,
+                offset: usize,
+                members: &mut ::std::vec::Vec<(::std::string::String, usize)>,
+            ) {
+                #(#members)*
+            }
+        }
+    })
+}
+
+fn std430(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let layout = Layout::Std430;
+    let fields = named_fields(input, layout.name())?;
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut size = quote!(0usize);
+    let mut struct_align = quote!(4usize);
+    let mut writes = Vec::new();
+    let mut reads = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let align = align(&field.ty, layout);
+        let field_size = self::size(&field.ty, layout);
+
+        let offset = quote!(::opengl::std140::align_to(#size, #align));
+        let write = write(
+            &field.ty,
+            layout,
+            quote!(&self.#ident),
+            quote!(&mut out[offset..]),
+        );
+        writes.push(quote! {
+            {
+                let offset = #offset;
+                #write
+            }
+        });
+        let read = read(&field.ty, quote!(&bytes[#offset..]));
+        reads.push(quote!(#ident: #read));
+        size = quote!(#offset + #field_size);
+        struct_align = quote!(::opengl::std430::max(#struct_align, #align));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::opengl::Std430 for #name #type_generics #where_clause {
+            // Unlike std140, structs are only as aligned as their largest member
+            const ALIGN: usize = #struct_align;
+            const SIZE: usize = ::opengl::std140::align_to(#size, Self::ALIGN);
+
+            fn write_std430(&self, out: &mut [u8]) {
+                #(#writes)*
+            }
+
+            fn read_std430(bytes: &[u8]) -> Self {
+                #name {
+                    #(#reads),*
+                }
+            }
+        }
+    })
+}
+
+// An array laid out by the array rules, rather than a vector the trait is implemented for
+fn array(ty: &Type) -> Option<(&Type, &Expr)> {
+    let Type::Array(array) = ty else {
+        return None;
+    };
+
+    let is_scalar = matches!(
+        &*array.elem,
+        Type::Path(path) if ["f32", "i32", "u32"].iter().any(|scalar| path.path.is_ident(scalar))
+    );
+    let is_vector_length = matches!(
+        &array.len,
+        Expr::Lit(length) if matches!(
+            &length.lit,
+            Lit::Int(length) if matches!(length.base10_digits(), "2" | "3" | "4")
+        )
+    );
+    match is_scalar && is_vector_length {
+        true => None,
+        false => Some((&array.elem, &array.len)),
+    }
+}
+
+fn align(ty: &Type, layout: Layout) -> TokenStream2 {
+    let trait_path = layout.trait_path();
+    match (array(ty), layout) {
+        // std140 rounds the alignment of arrays up to a vec4, std430 does not
+        (Some((element, _)), Layout::Std140) => {
+            let element = align(element, layout);
+            quote!(::opengl::std140::align_to(#element, 16))
+        }
+        (Some((element, _)), Layout::Std430) => align(element, layout),
+        (None, _) => quote!(<#ty as #trait_path>::ALIGN),
+    }
+}
+
+// The distance between array elements
+fn stride(element: &Type, layout: Layout) -> TokenStream2 {
+    let size = size(element, layout);
+    let align = align(element, layout);
+    match layout {
+        Layout::Std140 => quote!(::opengl::std140::align_to(#size, 16)),
+        Layout::Std430 => quote!(::opengl::std140::align_to(#size, #align)),
+    }
+}
+
+fn size(ty: &Type, layout: Layout) -> TokenStream2 {
+    let trait_path = layout.trait_path();
+    match array(ty) {
+        Some((element, length)) => {
+            let stride = stride(element, layout);
+            quote!((#stride * (#length)))
+        }
+        None => quote!(<#ty as #trait_path>::SIZE),
+    }
+}
+
+fn write(ty: &Type, layout: Layout, value: TokenStream2, out: TokenStream2) -> TokenStream2 {
+    let trait_path = layout.trait_path();
+    let write_fn = layout.write_fn();
+    match array(ty) {
+        Some((element, _)) => {
+            let stride = stride(element, layout);
+            let write = write(
+                element,
+                layout,
+                quote!(element),
+                quote!(&mut out[index * stride..]),
+            );
+            quote! {
+                {
+                    let out: &mut [u8] = #out;
+                    let stride = #stride;
+                    for (index, element) in (#value).iter().enumerate() {
+                        #write
+                    }
+                }
+            }
+        }
+        None => quote!(<#ty as #trait_path>::#write_fn(#value, #out);),
+    }
+}
+
+fn read(ty: &Type, bytes: TokenStream2) -> TokenStream2 {
+    match array(ty) {
+        Some((element, _)) => {
+            let stride = stride(element, Layout::Std430);
+            let read = read(element, quote!(&bytes[index * stride..]));
+            quote! {
+                {
+                    let bytes: &[u8] = #bytes;
+                    let stride = #stride;
+                    ::std::array::from_fn(|index| #read)
+                }
+            }
+        }
+        None => quote!(<#ty as ::opengl::Std430>::read_std430(#bytes)),
+    }
+}
+
+fn members(ty: &Type) -> TokenStream2 {
+    match array(ty) {
+        Some((element, length)) => {
+            let stride = stride(element, Layout::Std140);
+            let member = members(element);
+            // Programs report matrices by their own name and arrays by their first element
+            quote! {
+                members.push((name.clone(), offset));
+                let stride = #stride;
+                for index in 0..(#length) {
+                    let name = format!("{}[{}]", name, index);
+                    let offset = offset + index * stride;
+                    #member
+                }
+            }
+        }
+        None => quote!(<#ty as ::opengl::Std140>::std140_members(&name, offset, members);),
+    }
+}
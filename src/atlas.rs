@@ -0,0 +1,565 @@
+use crate::error::TextureError;
+use crate::texture::{Texture2D, TextureOptions};
+use image::{DynamicImage, GenericImageView, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where a sprite landed in an atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AtlasRegion {
+    /// Index of the page texture holding the sprite
+    pub page: usize,
+    /// Position and size in texels, without padding or extrusion
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Smallest texture coordinates of the sprite, its bottom left corner when the pages
+    /// are flipped vertically and its top left corner otherwise
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+/// The name to region lookup of an atlas, which can be saved alongside the page images.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasLookup {
+    pub page_width: u32,
+    pub page_height: u32,
+    pub pages: usize,
+    /// Whether the texture coordinates are for pages flipped vertically on upload, which
+    /// `Atlas::from_pages` then does whatever options it is given
+    pub flip_vertically: bool,
+    pub regions: BTreeMap<String, AtlasRegion>,
+}
+
+impl AtlasLookup {
+    pub fn get(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    pub fn to_json(&self) -> String {
+        // Nothing in the lookup can fail to serialize
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<AtlasLookup> {
+        serde_json::from_str(json)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<AtlasLookup> {
+        Ok(AtlasLookup::from_json(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Packs many small images into a few large pages, so they can be drawn from one texture.
+///
+/// Each sprite's edge texels are repeated `extrude` texels outwards, so filtering at the
+/// edge of a sprite reads its own colour, and `padding` empty texels keep its neighbours
+/// further away.
+pub struct AtlasBuilder {
+    page_width: u32,
+    page_height: u32,
+    padding: u32,
+    extrude: u32,
+    options: TextureOptions,
+    sprites: Vec<(String, DynamicImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new(page_width: u32, page_height: u32) -> AtlasBuilder {
+        AtlasBuilder {
+            page_width,
+            page_height,
+            padding: 1,
+            extrude: 1,
+            options: TextureOptions::default(),
+            sprites: Vec::new(),
+        }
+    }
+
+    /// Empty texels around each sprite, 1 by default.
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Times each sprite's edge texels are repeated outwards, 1 by default.
+    pub fn extrude(mut self, extrude: u32) -> Self {
+        self.extrude = extrude;
+        self
+    }
+
+    /// How `build` uploads the pages and whether the texture coordinates are flipped.
+    /// Mipmaps are always left out, see `Atlas::from_pages`.
+    pub fn options(mut self, options: TextureOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds a sprite, converted to RGBA8 when packed. Adding a name again replaces the
+    /// earlier image.
+    pub fn add(mut self, name: impl Into<String>, image: DynamicImage) -> Self {
+        let name = name.into();
+        match self
+            .sprites
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some(sprite) => sprite.1 = image,
+            None => self.sprites.push((name, image)),
+        }
+        self
+    }
+
+    /// Packs the sprites into page images without uploading them, to save them as files.
+    pub fn pack(&self) -> Result<(AtlasLookup, Vec<RgbaImage>), TextureError> {
+        if self.sprites.is_empty() {
+            return Err(TextureError::Empty);
+        }
+
+        // Largest first, small sprites then fill the gaps
+        let mut order: Vec<usize> = (0..self.sprites.len()).collect();
+        order.sort_by_key(|&index| {
+            let (width, height) = self.sprites[index].1.dimensions();
+            Reverse((width.max(height), width * height))
+        });
+
+        let margin = self.padding + self.extrude;
+        let mut packers: Vec<MaxRects> = Vec::new();
+        let mut pages: Vec<RgbaImage> = Vec::new();
+        let mut regions = BTreeMap::new();
+        for index in order {
+            let (name, image) = &self.sprites[index];
+            let (width, height) = image.dimensions();
+            if width == 0 || height == 0 {
+                return Err(TextureError::EmptySprite(name.clone()));
+            }
+            let (slot_width, slot_height) = (width + 2 * margin, height + 2 * margin);
+            if slot_width > self.page_width || slot_height > self.page_height {
+                return Err(TextureError::SpriteTooLarge {
+                    name: name.clone(),
+                    width,
+                    height,
+                });
+            }
+
+            let placed = packers.iter_mut().enumerate().find_map(|(page, packer)| {
+                packer
+                    .insert(slot_width, slot_height)
+                    .map(|slot| (page, slot))
+            });
+            let (page, slot) = match placed {
+                Some(placed) => placed,
+                None => {
+                    let mut packer = MaxRects::new(self.page_width, self.page_height);
+                    let slot = packer.insert(slot_width, slot_height).unwrap();
+                    packers.push(packer);
+                    pages.push(RgbaImage::new(self.page_width, self.page_height));
+                    (pages.len() - 1, slot)
+                }
+            };
+
+            let (x, y) = (slot.x + margin, slot.y + margin);
+            blit_extruded(&mut pages[page], &image.to_rgba8(), x, y, self.extrude);
+            regions.insert(name.clone(), self.region(page, x, y, width, height));
+        }
+
+        let lookup = AtlasLookup {
+            page_width: self.page_width,
+            page_height: self.page_height,
+            pages: pages.len(),
+            flip_vertically: self.options.flip_vertically,
+            regions,
+        };
+        Ok((lookup, pages))
+    }
+
+    /// Packs the sprites and uploads each page as a texture.
+    pub fn build(self) -> Result<Atlas, TextureError> {
+        let (lookup, pages) = self.pack()?;
+        let pages: Vec<DynamicImage> = pages.into_iter().map(DynamicImage::ImageRgba8).collect();
+        Atlas::from_pages(lookup, &pages, self.options)
+    }
+
+    // Texture coordinates follow the pages, which may be flipped on upload
+    fn region(&self, page: usize, x: u32, y: u32, width: u32, height: u32) -> AtlasRegion {
+        let (page_width, page_height) = (self.page_width as f32, self.page_height as f32);
+        let (u_min, u_max) = (x as f32 / page_width, (x + width) as f32 / page_width);
+        let (top, bottom) = (y as f32 / page_height, (y + height) as f32 / page_height);
+        let (v_min, v_max) = match self.options.flip_vertically {
+            true => (1.0 - bottom, 1.0 - top),
+            false => (top, bottom),
+        };
+
+        AtlasRegion {
+            page,
+            x,
+            y,
+            width,
+            height,
+            uv_min: [u_min, v_min],
+            uv_max: [u_max, v_max],
+        }
+    }
+}
+
+// Copy `sprite` to `x`, `y` of `page`, repeating its edge texels `extrude` times outwards
+fn blit_extruded(page: &mut RgbaImage, sprite: &RgbaImage, x: u32, y: u32, extrude: u32) {
+    let (width, height) = sprite.dimensions();
+    let extrude = extrude as i64;
+    for dy in -extrude..height as i64 + extrude {
+        for dx in -extrude..width as i64 + extrude {
+            let source_x = dx.clamp(0, width as i64 - 1) as u32;
+            let source_y = dy.clamp(0, height as i64 - 1) as u32;
+            let pixel = *sprite.get_pixel(source_x, source_y);
+            page.put_pixel((x as i64 + dx) as u32, (y as i64 + dy) as u32, pixel);
+        }
+    }
+}
+
+/// Page textures and the lookup of where each sprite is on them.
+pub struct Atlas {
+    pages: Vec<Texture2D>,
+    lookup: AtlasLookup,
+}
+
+impl Atlas {
+    /// Uploads page images saved from `AtlasBuilder::pack`, in page order.
+    ///
+    /// The pages are flipped the way the lookup's texture coordinates expect and get no
+    /// mipmaps: padding and extrusion only keep sprites apart on the full size level, the
+    /// smaller levels would blend neighbours into each other.
+    pub fn from_pages(
+        lookup: AtlasLookup,
+        pages: &[DynamicImage],
+        options: TextureOptions,
+    ) -> Result<Atlas, TextureError> {
+        if pages.len() != lookup.pages {
+            return Err(TextureError::PageCount {
+                expected: lookup.pages,
+                found: pages.len(),
+            });
+        }
+        for page in pages {
+            let found = page.dimensions();
+            let expected = (lookup.page_width, lookup.page_height);
+            if found != expected {
+                return Err(TextureError::SizeMismatch { expected, found });
+            }
+        }
+
+        let options = TextureOptions {
+            flip_vertically: lookup.flip_vertically,
+            mipmaps: false,
+            cpu_mipmaps: None,
+            ..options
+        };
+        let pages = pages
+            .iter()
+            .map(|page| Texture2D::from_image(page, options))
+            .collect();
+        Ok(Atlas { pages, lookup })
+    }
+
+    pub fn pages(&self) -> &[Texture2D] {
+        &self.pages
+    }
+
+    pub fn page(&self, index: usize) -> &Texture2D {
+        &self.pages[index]
+    }
+
+    pub fn lookup(&self) -> &AtlasLookup {
+        &self.lookup
+    }
+
+    /// Where the sprite called `name` is, or `None` if there is no such sprite.
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.lookup.get(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+}
+
+// MaxRects packing, which tracks the free space of a page as every largest empty rectangle,
+// overlapping each other
+struct MaxRects {
+    free: Vec<Rect>,
+}
+
+impl MaxRects {
+    fn new(width: u32, height: u32) -> MaxRects {
+        MaxRects {
+            free: vec![Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }],
+        }
+    }
+
+    // Place a rectangle where it leaves the shortest side over, or `None` if it fits nowhere
+    fn insert(&mut self, width: u32, height: u32) -> Option<Rect> {
+        let best = self
+            .free
+            .iter()
+            .filter(|free| free.width >= width && free.height >= height)
+            .min_by_key(|free| {
+                let (left_x, left_y) = (free.width - width, free.height - height);
+                (left_x.min(left_y), left_x.max(left_y))
+            })?;
+        let placed = Rect {
+            x: best.x,
+            y: best.y,
+            width,
+            height,
+        };
+
+        // Replace each free rectangle the placed one overlaps with its parts to the left,
+        // right, top and bottom of it
+        let mut free = Vec::with_capacity(self.free.len() + 4);
+        for rect in &self.free {
+            if !rect.intersects(&placed) {
+                free.push(*rect);
+                continue;
+            }
+            if placed.x > rect.x {
+                free.push(Rect {
+                    width: placed.x - rect.x,
+                    ..*rect
+                });
+            }
+            if placed.right() < rect.right() {
+                free.push(Rect {
+                    x: placed.right(),
+                    width: rect.right() - placed.right(),
+                    ..*rect
+                });
+            }
+            if placed.y > rect.y {
+                free.push(Rect {
+                    height: placed.y - rect.y,
+                    ..*rect
+                });
+            }
+            if placed.bottom() < rect.bottom() {
+                free.push(Rect {
+                    y: placed.bottom(),
+                    height: rect.bottom() - placed.bottom(),
+                    ..*rect
+                });
+            }
+        }
+
+        // Rectangles inside others add nothing, of identical ones the first stays
+        self.free = free
+            .iter()
+            .enumerate()
+            .filter(|&(index, rect)| {
+                !free.iter().enumerate().any(|(other_index, other)| {
+                    other_index != index
+                        && other.contains(rect)
+                        && (other != rect || other_index < index)
+                })
+            })
+            .map(|(_, rect)| *rect)
+            .collect();
+        Some(placed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(rects: &[Rect]) -> bool {
+        rects.iter().enumerate().any(|(index, rect)| {
+            rects[index + 1..]
+                .iter()
+                .any(|other| rect.intersects(other))
+        })
+    }
+
+    #[test]
+    fn max_rects_never_overlap() {
+        let mut packer = MaxRects::new(64, 64);
+        let mut placed = Vec::new();
+        for index in 0..40u32 {
+            let (width, height) = (3 + index * 7 % 13, 2 + index * 5 % 11);
+            if let Some(rect) = packer.insert(width, height) {
+                assert!(rect.right() <= 64 && rect.bottom() <= 64);
+                placed.push(rect);
+            }
+        }
+        assert!(placed.len() > 20);
+        assert!(!overlaps(&placed));
+        for free in &packer.free {
+            assert!(placed.iter().all(|rect| !rect.intersects(free)));
+        }
+    }
+
+    #[test]
+    fn max_rects_fill_and_overflow() {
+        let mut packer = MaxRects::new(32, 32);
+        for _ in 0..16 {
+            assert!(packer.insert(8, 8).is_some());
+        }
+        assert!(packer.free.is_empty());
+        assert_eq!(packer.insert(1, 1), None);
+
+        let mut packer = MaxRects::new(32, 32);
+        assert_eq!(packer.insert(33, 1), None);
+        assert!(packer.insert(32, 32).is_some());
+    }
+
+    fn sprite(width: u32, height: u32, value: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            width,
+            height,
+            image::Rgba([value, value, value, 255]),
+        ))
+    }
+
+    #[test]
+    fn pack_spills_onto_new_pages() {
+        let mut builder = AtlasBuilder::new(32, 32).padding(1).extrude(1);
+        for index in 0..6 {
+            builder = builder.add(format!("sprite{index}"), sprite(12, 12, index as u8));
+        }
+        let (lookup, pages) = builder.pack().unwrap();
+
+        // Four 16x16 slots fit on a page
+        assert_eq!(pages.len(), 2);
+        assert_eq!(lookup.pages, 2);
+        assert_eq!(lookup.regions.len(), 6);
+        for page in 0..2 {
+            let rects: Vec<Rect> = lookup
+                .regions
+                .values()
+                .filter(|region| region.page == page)
+                .map(|region| Rect {
+                    x: region.x - 2,
+                    y: region.y - 2,
+                    width: region.width + 4,
+                    height: region.height + 4,
+                })
+                .collect();
+            assert!(!overlaps(&rects));
+        }
+    }
+
+    #[test]
+    fn pack_extrudes_edges() {
+        let (lookup, pages) = AtlasBuilder::new(16, 16)
+            .padding(1)
+            .extrude(2)
+            .add("a", sprite(4, 4, 200))
+            .pack()
+            .unwrap();
+        let region = lookup.get("a").unwrap();
+        assert_eq!((region.x, region.y), (3, 3));
+        assert_eq!(pages[0].get_pixel(1, 1)[0], 200);
+        assert_eq!(pages[0].get_pixel(0, 0)[3], 0);
+        assert_eq!(region.uv_min, [3.0 / 16.0, 3.0 / 16.0]);
+        assert_eq!(region.uv_max, [7.0 / 16.0, 7.0 / 16.0]);
+    }
+
+    #[test]
+    fn adding_a_name_again_replaces_the_image() {
+        let (lookup, pages) = AtlasBuilder::new(64, 64)
+            .add("a", sprite(30, 30, 1))
+            .add("a", sprite(4, 4, 2))
+            .pack()
+            .unwrap();
+        let region = lookup.get("a").unwrap();
+        assert_eq!((region.width, region.height), (4, 4));
+        assert_eq!(pages[0].get_pixel(region.x, region.y)[0], 2);
+    }
+
+    #[test]
+    fn pack_rejects_bad_sprites() {
+        let empty = AtlasBuilder::new(16, 16)
+            .add("empty", sprite(0, 4, 0))
+            .pack();
+        assert!(matches!(empty, Err(TextureError::EmptySprite(name)) if name == "empty"));
+
+        let large = AtlasBuilder::new(16, 16)
+            .add("large", sprite(15, 4, 0))
+            .pack();
+        assert!(matches!(large, Err(TextureError::SpriteTooLarge { .. })));
+
+        assert!(matches!(
+            AtlasBuilder::new(16, 16).pack(),
+            Err(TextureError::Empty)
+        ));
+    }
+
+    #[test]
+    fn lookup_round_trips_through_json() {
+        let (lookup, _) = AtlasBuilder::new(32, 32)
+            .add("a", sprite(5, 7, 0))
+            .add("b", sprite(3, 2, 0))
+            .pack()
+            .unwrap();
+        assert_eq!(AtlasLookup::from_json(&lookup.to_json()).unwrap(), lookup);
+    }
+
+    #[test]
+    fn lookup_records_the_flip() {
+        let pack = |flip_vertically| {
+            AtlasBuilder::new(32, 32)
+                .options(TextureOptions {
+                    flip_vertically,
+                    ..TextureOptions::default()
+                })
+                .add("a", sprite(8, 4, 0))
+                .pack()
+                .unwrap()
+                .0
+        };
+
+        let (upright, flipped) = (pack(false), pack(true));
+        assert!(!upright.flip_vertically && flipped.flip_vertically);
+        let (a, b) = (upright.get("a").unwrap(), flipped.get("a").unwrap());
+        assert_eq!((a.x, a.y), (b.x, b.y));
+        assert_eq!(a.uv_min[1], 1.0 - b.uv_max[1]);
+        assert_eq!(a.uv_max[1], 1.0 - b.uv_min[1]);
+    }
+}
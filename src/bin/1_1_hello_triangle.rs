@@ -1,6 +1,7 @@
 use core::str;
 use gl::types::*;
 use glfw::{Action, Context, Key};
+use opengl::{Buffer, BufferTarget, BufferUsage};
 use std::ptr;
 
 const VERTEX_SHADER_SOURCE: &str = "#version 330 core
@@ -69,7 +70,7 @@ fn main() {
     ];
 
     // Obtain the shader program and vertex array object
-    let (shader_program, vao) = unsafe {
+    let (shader_program, vao, _vbo, _ebo) = unsafe {
         // Create vertex shader
         let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
         assert_ne!(vertex_shader, 0);
@@ -162,25 +163,13 @@ fn main() {
         gl::BindVertexArray(vao);
 
         // Create vertex buffer object
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            size_of_val(&vertices) as isize,
-            vertices.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
+        let vbo = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, &vertices);
 
         // Create element buffer object
-        let mut ebo = 0;
-        gl::GenBuffers(1, &mut ebo);
-        gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
-        gl::BufferData(
-            gl::ELEMENT_ARRAY_BUFFER,
-            size_of_val(&indices) as isize,
-            indices.as_ptr() as *const _,
-            gl::STATIC_DRAW,
+        let ebo = Buffer::from_slice(
+            BufferTarget::ElementArray,
+            BufferUsage::StaticDraw,
+            &indices,
         );
 
         // Create vertex attribute pointer
@@ -198,7 +187,7 @@ fn main() {
         gl::BindVertexArray(0);
 
         // Return shader program and vertex array object
-        (shader_program, vao)
+        (shader_program, vao, vbo, ebo)
     };
 
     // Main loop
@@ -1,6 +1,7 @@
 use core::str;
 use gl::types::*;
 use glfw::{Action, Context, Key};
+use opengl::{Buffer, BufferTarget, BufferUsage};
 use std::ptr;
 
 const VERTEX_SHADER_SOURCE: &str = "#version 330 core
@@ -64,7 +65,7 @@ fn main() {
     ];
 
     // Obtain the shader program and vertex array object
-    let (shader_program, vao) = unsafe {
+    let (shader_program, vao, _vbo) = unsafe {
         // Create vertex shader
         let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
         assert_ne!(vertex_shader, 0);
@@ -157,15 +158,7 @@ fn main() {
         gl::BindVertexArray(vao);
 
         // Create vertex buffer object
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            size_of_val(&vertices) as isize,
-            vertices.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
+        let vbo = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, &vertices);
 
         // Create vertex attribute pointer
         gl::VertexAttribPointer(
@@ -182,7 +175,7 @@ fn main() {
         gl::BindVertexArray(0);
 
         // Return shader program and vertex array object
-        (shader_program, vao)
+        (shader_program, vao, vbo)
     };
 
     // Main loop
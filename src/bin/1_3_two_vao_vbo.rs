@@ -1,6 +1,7 @@
 use core::str;
 use gl::types::*;
 use glfw::{Action, Context, Key};
+use opengl::{Buffer, BufferTarget, BufferUsage};
 use std::ptr;
 
 const VERTEX_SHADER_SOURCE: &str = "#version 330 core
@@ -64,7 +65,7 @@ fn main() {
     ];
 
     // Obtain the shader program and vertex array object
-    let (shader_program, vao1, vao2) = unsafe {
+    let (shader_program, vao1, vao2, _vbo1, _vbo2) = unsafe {
         // Create vertex shader
         let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
         assert_ne!(vertex_shader, 0);
@@ -157,15 +158,7 @@ fn main() {
         gl::BindVertexArray(vao1);
 
         // Create vertex buffer object
-        let mut vbo1 = 0;
-        gl::GenBuffers(1, &mut vbo1);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo1);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (size_of_val(&vertices) / 2) as isize,
-            vertices.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
+        let vbo1 = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, &vertices[..9]);
 
         // Create vertex attribute pointer
         gl::VertexAttribPointer(
@@ -187,15 +180,7 @@ fn main() {
         gl::BindVertexArray(vao2);
 
         // Create another vertex buffer object
-        let mut vbo2 = 0;
-        gl::GenBuffers(1, &mut vbo2);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo2);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (size_of_val(&vertices) / 2) as isize,
-            vertices.as_ptr().offset(9) as *const _,
-            gl::STATIC_DRAW,
-        );
+        let vbo2 = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, &vertices[9..]);
 
         // Create another vertex attribute pointer
         gl::VertexAttribPointer(
@@ -212,7 +197,7 @@ fn main() {
         gl::BindVertexArray(0);
 
         // Return shader program and vertex array object
-        (shader_program, vao1, vao2)
+        (shader_program, vao1, vao2, vbo1, vbo2)
     };
 
     // Main loop
@@ -1,6 +1,7 @@
 use core::str;
 use gl::types::*;
 use glfw::{Action, Context, Key};
+use opengl::{Buffer, BufferTarget, BufferUsage};
 use std::{
     ffi::{c_void, CString},
     ptr,
@@ -71,7 +72,7 @@ fn main() {
     ];
 
     // Obtain the shader program and vertex array object
-    let (shader_program, vao) = unsafe {
+    let (shader_program, vao, _vbo) = unsafe {
         // Create vertex shader
         let vertex_shader = gl::CreateShader(gl::VERTEX_SHADER);
         assert_ne!(vertex_shader, 0);
@@ -164,15 +165,7 @@ fn main() {
         gl::BindVertexArray(vao);
 
         // Create vertex buffer object
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            size_of_val(&vertices) as isize,
-            vertices.as_ptr() as *const _,
-            gl::STATIC_DRAW,
-        );
+        let vbo = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, &vertices);
 
         // Create vertex attribute pointer
         gl::VertexAttribPointer(
@@ -199,7 +192,7 @@ fn main() {
         gl::BindVertexArray(0);
 
         // Return shader program and vertex array object
-        (shader_program_red, vao)
+        (shader_program_red, vao, vbo)
     };
 
     unsafe {
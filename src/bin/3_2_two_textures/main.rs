@@ -1,13 +1,19 @@
 use gl::types::*;
 use glfw::{Action, Context, Key};
-use image::metadata::Orientation;
-use std::ffi::c_void;
+use opengl::{
+    context, ColorSpace, Mesh, Pod, Primitive, Sampler, SamplerDesc, Shader, Texture2D,
+    TextureOptions, TextureUnit, Vertex, Zeroable,
+};
 use std::path::Path;
-use std::ptr;
-use utils::Shader;
 extern crate image;
 
-pub mod utils;
+// A corner of the quad and where it samples the textures
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Vertex)]
+struct TexturedVertex {
+    position: [f32; 2],
+    texture_coordinates: [f32; 2],
+}
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 800;
@@ -23,6 +29,8 @@ fn main() {
         glfw::OpenGlProfileHint::Core,
     ));
 
+    glfw.window_hint(glfw::WindowHint::SRgbCapable(true));
+
     // Initialize Window
     let (mut window, events) = glfw
         .create_window(
@@ -48,150 +56,67 @@ fn main() {
         gl::Viewport(0, 0, WIDTH as GLint, HEIGHT as GLint);
     }
 
+    context::set_framebuffer_srgb(true);
+
     // Declare the vertices as the whole screen
     #[rustfmt::skip]
-    let vertices= [
-        //Positions //Textures
-        -0.5, -0.5, 0.0, 0.0,
-        -0.5,  0.5, 0.0, 1.0,
-         0.5, -0.5, 1.0, 0.0,
-        -0.5,  0.5, 0.0, 1.0,
-         0.5, -0.5, 1.0, 0.0,
-         0.5,  0.5, 1.0, 1.0f32,
+    let vertices = [
+        TexturedVertex { position: [-0.5, -0.5], texture_coordinates: [0.0, 0.0] },
+        TexturedVertex { position: [-0.5,  0.5], texture_coordinates: [0.0, 1.0] },
+        TexturedVertex { position: [ 0.5, -0.5], texture_coordinates: [1.0, 0.0] },
+        TexturedVertex { position: [ 0.5,  0.5], texture_coordinates: [1.0, 1.0] },
     ];
 
-    // Obtain the shader program and vertex array object
-    let (shader_program, vao, texture1, texture2) = unsafe {
+    // Two triangles sharing the diagonal
+    let indices: [u16; 6] = [0, 1, 2, 1, 2, 3];
+
+    // Obtain the shader program and quad
+    let (shader, quad, texture1, texture2) = unsafe {
         // Create shader from file
         let shader_class = Shader::from_file(Path::new("vertex.glsl"), Path::new("fragment.glsl"))
             .expect("Cannot create shader class");
 
-        // Create vertex array object
-        let mut vao = 0;
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-
-        // Create vertex buffer object
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (size_of_val(&vertices)) as isize,
-            vertices.as_ptr() as *const c_void,
-            gl::STATIC_DRAW,
-        );
-
-        // Create vertex attribute pointer
-        // This one is for position
-        gl::VertexAttribPointer(
-            0,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            4 * size_of_val(&vertices[0]) as i32,
-            ptr::null::<c_void>(),
-        );
-        gl::EnableVertexAttribArray(0);
-
-        // This one is for texture
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            4 * size_of_val(&vertices[0]) as i32,
-            (2 * size_of_val(&vertices[0])) as *const c_void,
-            // ptr::null::<c_void>(),
-        );
-        gl::EnableVertexAttribArray(1);
+        // Upload the quad, the attributes come from the vertex type
+        let quad = Mesh::indexed(Primitive::Triangles, &vertices, &indices);
 
         // Create the container texture
         let img = image::open(Path::new("./container.jpg")).expect("Cannot load texture image.");
-        let data = img
-            .as_flat_samples_u8()
-            .expect("Cannot flatten texture image.");
-
-        let mut texture1 = 0;
-
-        gl::GenTextures(1, &mut texture1);
-
-        // Active texture is 0 by default
-        gl::ActiveTexture(gl::TEXTURE0);
-
-        gl::BindTexture(gl::TEXTURE_2D, texture1);
-        gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGB as i32,
-            img.width() as i32,
-            img.height() as i32,
-            0,
-            gl::RGB,
-            gl::UNSIGNED_BYTE,
-            data.samples.as_ptr() as *const c_void,
+        let texture1 = Texture2D::from_image(
+            &img,
+            TextureOptions {
+                color_space: ColorSpace::Srgb,
+                ..Default::default()
+            },
         );
-        gl::GenerateMipmap(gl::TEXTURE_2D);
-        gl::BindTexture(gl::TEXTURE_2D, 0);
 
         // Create the moai texture
-        let mut img = image::open(Path::new("./moai.png")).expect("Cannot load texture image.");
-        img.apply_orientation(Orientation::FlipVertical);
-        let data = img
-            .as_flat_samples_u8()
-            .expect("Cannot flatten texture image.");
-
-        let mut texture2 = 0;
-        gl::GenTextures(1, &mut texture2);
-
-        gl::ActiveTexture(gl::TEXTURE1);
-
-        gl::BindTexture(gl::TEXTURE_2D, texture2);
-        gl::TexImage2D(
-            gl::TEXTURE_2D,
-            0,
-            gl::RGBA as i32,
-            img.width() as i32,
-            img.height() as i32,
-            0,
-            gl::RGBA,
-            gl::UNSIGNED_BYTE,
-            data.samples.as_ptr() as *const c_void,
-        );
-        gl::GenerateMipmap(gl::TEXTURE_2D);
-        gl::BindTexture(gl::TEXTURE_2D, 0);
-
-        // Set texture options
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
-        gl::TexParameteri(
-            gl::TEXTURE_2D,
-            gl::TEXTURE_MIN_FILTER,
-            gl::LINEAR_MIPMAP_LINEAR as i32,
+        let img = image::open(Path::new("./moai.png")).expect("Cannot load texture image.");
+        let texture2 = Texture2D::from_image(
+            &img,
+            TextureOptions {
+                flip_vertically: true,
+                color_space: ColorSpace::Srgb,
+                ..Default::default()
+            },
         );
 
-        // Unbind vertex array object
-        gl::BindVertexArray(0);
         gl::ActiveTexture(gl::TEXTURE0);
-        // Return shader program and vertex array object
+        // Return shader program and quad
 
-        (shader_class.get(), vao, texture1, texture2)
+        (shader_class, quad, texture1, texture2)
     };
+    let shader_program = shader.get();
 
-    unsafe {
-        // Set texture uniforms
-        gl::UseProgram(shader_program);
-        gl::Uniform1i(
-            gl::GetUniformLocation(shader_program, "texture1\0".as_ptr() as *const i8),
-            0,
-        );
-        gl::Uniform1i(
-            gl::GetUniformLocation(shader_program, "texture2\0".as_ptr() as *const i8),
-            1,
-        );
-        gl::UseProgram(0);
-    };
+    // Repeat and filter with mipmaps, shared by both textures
+    let sampler = Sampler::new(&SamplerDesc::default());
+
+    // Set texture uniforms
+    shader
+        .set_uniform("texture1", TextureUnit(0))
+        .expect("Cannot set texture1 uniform");
+    shader
+        .set_uniform("texture2", TextureUnit(1))
+        .expect("Cannot set texture2 uniform");
 
     // Main loop
     while !window.should_close() {
@@ -206,20 +131,16 @@ fn main() {
             gl::ClearColor(1., 1., 1., 1.);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, texture1);
-            gl::ActiveTexture(gl::TEXTURE1);
-            gl::BindTexture(gl::TEXTURE_2D, texture2);
-            gl::BindVertexArray(vao);
+            texture1.bind_with_sampler(TextureUnit(0), &sampler);
+            texture2.bind_with_sampler(TextureUnit(1), &sampler);
             gl::UseProgram(shader_program);
 
-            gl::DrawArrays(gl::TRIANGLES, 0, 6);
+            quad.draw();
 
             gl::ActiveTexture(gl::TEXTURE1);
             gl::BindTexture(gl::TEXTURE_2D, 0);
             gl::ActiveTexture(gl::TEXTURE0);
             gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::BindVertexArray(0);
             gl::UseProgram(0);
         }
 
@@ -1,10 +1,14 @@
 use gl::types::*;
 use glfw::{Action, Context, Key};
-use std::ffi::c_void;
-use std::ptr;
-use utils::Shader;
-
-pub mod utils;
+use opengl::{Mesh, Pod, Primitive, ReloadableShader, Vertex, Zeroable};
+use std::path::Path;
+
+// A corner of the screen
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Vertex)]
+struct ScreenVertex {
+    position: [f32; 2],
+}
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 800;
@@ -44,63 +48,36 @@ fn main() {
 
     // Declare the vertices as the whole screen
     #[rustfmt::skip]
-    let vertices: [f32; 12] = [
-        -1.0, -1.0,
-        -1.0, 1.0,
-        1.0, -1.0,
-        -1.0, 1.0,
-        1.0, -1.0,
-        1.0, 1.0,
+    let vertices = [
+        ScreenVertex { position: [-1.0, -1.0] },
+        ScreenVertex { position: [-1.0,  1.0] },
+        ScreenVertex { position: [ 1.0, -1.0] },
+        ScreenVertex { position: [ 1.0,  1.0] },
     ];
 
-    // Obtain the shader program and vertex array object
-    let (shader_program, vao) = unsafe {
-        // Create shader from file
+    // Two triangles sharing the diagonal
+    let indices: [u16; 6] = [0, 1, 2, 1, 2, 3];
+
+    // Obtain the shader program and quad
+    let (mut shader, quad) = {
+        // Create shader from file, it is rebuilt whenever the files are edited
         let shader_class =
-            Shader::new("vertex.glsl", "fragment.glsl").expect("Cannot create shader class");
-
-        // Create vertex array object
-        let mut vao = 0;
-        gl::GenVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
-
-        // Create vertex buffer object
-        let mut vbo = 0;
-        gl::GenBuffers(1, &mut vbo);
-        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-        gl::BufferData(
-            gl::ARRAY_BUFFER,
-            (size_of_val(&vertices)) as isize,
-            vertices.as_ptr() as *const c_void,
-            gl::STATIC_DRAW,
-        );
-
-        // Create vertex attribute pointer
-        gl::VertexAttribPointer(
-            0,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            2 * size_of_val(&vertices[0]) as i32,
-            ptr::null::<c_void>(),
-        );
-        gl::EnableVertexAttribArray(0);
-
-        // Unbind vertex array object
-        gl::BindVertexArray(0);
-        // Return shader program and vertex array object
-
-        (shader_class.get(), vao)
+            ReloadableShader::from_file(Path::new("vertex.glsl"), Path::new("fragment.glsl"))
+                .expect("Cannot create shader class");
+
+        // Upload the quad, the attributes come from the vertex type
+        let quad = Mesh::indexed(Primitive::Triangles, &vertices, &indices);
+
+        // Return shader program and quad
+
+        (shader_class, quad)
     };
 
     // Pass window size
-    unsafe {
-        let window_size_location =
-            gl::GetUniformLocation(shader_program, "windowSize\0".as_ptr() as *const i8);
-        gl::UseProgram(shader_program);
-        gl::Uniform2f(window_size_location, WIDTH as f32, HEIGHT as f32);
-        gl::UseProgram(0);
-    }
+    shader
+        .shader()
+        .set_uniform("windowSize", [WIDTH as f32, HEIGHT as f32])
+        .expect("Cannot set windowSize uniform");
 
     // Start time counter
     let time_start = std::time::Instant::now();
@@ -113,21 +90,25 @@ fn main() {
             handle_window_event(&mut window, event);
         }
 
+        // A rebuilt program starts with fresh uniforms, and they may have been edited out
+        if shader.reload_if_changed() {
+            shader
+                .shader()
+                .set_uniform("windowSize", [WIDTH as f32, HEIGHT as f32])
+                .ok();
+        }
+
         // Draw to the backbuffer
         unsafe {
             gl::ClearColor(1., 1., 1., 1.);
             gl::Clear(gl::COLOR_BUFFER_BIT);
 
-            gl::UseProgram(shader_program);
-            gl::BindVertexArray(vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, 6);
+            gl::UseProgram(shader.get());
+            quad.draw();
 
-            let value_location =
-                gl::GetUniformLocation(shader_program, "time\0".as_ptr() as *const i8);
             let time_elapsed = time_start.elapsed().as_millis() as f32 / 1000.0;
-            gl::Uniform1f(value_location, time_elapsed);
+            shader.shader().set_uniform("time", time_elapsed).ok();
 
-            gl::BindVertexArray(0);
             gl::UseProgram(0);
         }
 
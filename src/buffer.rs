@@ -0,0 +1,259 @@
+use bytemuck::Pod;
+use gl::types::*;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BufferTarget {
+    /// Vertex data, `GL_ARRAY_BUFFER`
+    Array,
+    /// Indices, `GL_ELEMENT_ARRAY_BUFFER`
+    ElementArray,
+    Uniform,
+    ShaderStorage,
+    /// Draw commands read by `Mesh::multi_draw_indirect`
+    DrawIndirect,
+}
+
+impl BufferTarget {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            BufferTarget::Array => gl::ARRAY_BUFFER,
+            BufferTarget::ElementArray => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::Uniform => gl::UNIFORM_BUFFER,
+            BufferTarget::ShaderStorage => gl::SHADER_STORAGE_BUFFER,
+            BufferTarget::DrawIndirect => gl::DRAW_INDIRECT_BUFFER,
+        }
+    }
+
+    /// The target writes and reads bind to. Binding `GL_ELEMENT_ARRAY_BUFFER` attaches the
+    /// buffer to the vertex array in use, so index data goes through `GL_COPY_WRITE_BUFFER`.
+    pub(crate) fn transfer_enum(self) -> GLenum {
+        match self {
+            BufferTarget::ElementArray => gl::COPY_WRITE_BUFFER,
+            target => target.gl_enum(),
+        }
+    }
+}
+
+/// How often the data is written and who reads it, a hint for where the driver keeps it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BufferUsage {
+    StaticDraw,
+    DynamicDraw,
+    StreamDraw,
+    StaticRead,
+    DynamicRead,
+    StreamRead,
+    StaticCopy,
+    DynamicCopy,
+    StreamCopy,
+}
+
+impl BufferUsage {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            BufferUsage::StaticDraw => gl::STATIC_DRAW,
+            BufferUsage::DynamicDraw => gl::DYNAMIC_DRAW,
+            BufferUsage::StreamDraw => gl::STREAM_DRAW,
+            BufferUsage::StaticRead => gl::STATIC_READ,
+            BufferUsage::DynamicRead => gl::DYNAMIC_READ,
+            BufferUsage::StreamRead => gl::STREAM_READ,
+            BufferUsage::StaticCopy => gl::STATIC_COPY,
+            BufferUsage::DynamicCopy => gl::DYNAMIC_COPY,
+            BufferUsage::StreamCopy => gl::STREAM_COPY,
+        }
+    }
+}
+
+/// Owns a buffer object holding elements of `T`, which is deleted when dropped.
+///
+/// Writes past the end grow the buffer in place, so the name stays the same and vertex
+/// arrays that point at it stay valid.
+pub struct Buffer<T: Pod> {
+    buffer: GLuint,
+    target: BufferTarget,
+    usage: BufferUsage,
+    len: usize,
+    capacity: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Pod> Buffer<T> {
+    /// An empty buffer, storage is allocated by the first upload.
+    pub fn new(target: BufferTarget, usage: BufferUsage) -> Buffer<T> {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+        }
+
+        Buffer {
+            buffer,
+            target,
+            usage,
+            len: 0,
+            capacity: 0,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn from_slice(target: BufferTarget, usage: BufferUsage, data: &[T]) -> Buffer<T> {
+        let mut buffer = Buffer::new(target, usage);
+        buffer.upload(data);
+        buffer
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.buffer
+    }
+
+    pub fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    /// Number of elements written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the storage has room for.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Binds the buffer to its target. Binding an element array buffer attaches it to the
+    /// vertex array in use.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBuffer(self.target.gl_enum(), self.buffer);
+        }
+    }
+
+    // Bind without touching the vertex array in use, returning the target bound to
+    fn bind_for_transfer(&self) -> GLenum {
+        let target = self.target.transfer_enum();
+        unsafe {
+            gl::BindBuffer(target, self.buffer);
+        }
+        target
+    }
+
+    /// Replaces the contents with `data`, reallocating only when it does not fit.
+    pub fn upload(&mut self, data: &[T]) {
+        let target = self.bind_for_transfer();
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        unsafe {
+            if data.len() > self.capacity {
+                gl::BufferData(
+                    target,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_ptr() as *const _,
+                    self.usage.gl_enum(),
+                );
+                self.capacity = data.len();
+            } else if !bytes.is_empty() {
+                gl::BufferSubData(
+                    target,
+                    0,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_ptr() as *const _,
+                );
+            }
+        }
+        self.len = data.len();
+    }
+
+    /// Overwrites the elements starting at `offset`, growing the buffer if they run past the
+    /// end. Growing keeps the elements already there.
+    pub fn update_range(&mut self, offset: usize, data: &[T]) {
+        let end = offset + data.len();
+        if end > self.capacity {
+            self.reserve(end);
+        }
+
+        let target = self.bind_for_transfer();
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        if !bytes.is_empty() {
+            unsafe {
+                gl::BufferSubData(
+                    target,
+                    (offset * size_of::<T>()) as GLintptr,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_ptr() as *const _,
+                );
+            }
+        }
+        self.len = self.len.max(end);
+    }
+
+    /// Copies the elements back to the CPU, stalling until the GPU is done with the buffer.
+    pub fn read_back(&self) -> Vec<T> {
+        let mut data = vec![T::zeroed(); self.len];
+        if !data.is_empty() {
+            let target = self.bind_for_transfer();
+            let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut data);
+            unsafe {
+                gl::GetBufferSubData(
+                    target,
+                    0,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_mut_ptr() as *mut _,
+                );
+            }
+        }
+        data
+    }
+
+    // Make room for at least `capacity` elements, doubling to keep repeated growth cheap.
+    // The contents go through a temporary buffer and back without leaving the GPU, so the
+    // name stays the same and nothing waits for pending draws on the CPU
+    fn reserve(&mut self, capacity: usize) {
+        let capacity = capacity.max(self.capacity * 2);
+        let bytes = (self.len * size_of::<T>()) as GLsizeiptr;
+        unsafe {
+            let mut temporary = 0;
+            if bytes > 0 {
+                gl::GenBuffers(1, &mut temporary);
+                gl::BindBuffer(gl::COPY_READ_BUFFER, self.buffer);
+                gl::BindBuffer(gl::COPY_WRITE_BUFFER, temporary);
+                gl::BufferData(
+                    gl::COPY_WRITE_BUFFER,
+                    bytes,
+                    std::ptr::null(),
+                    gl::STREAM_COPY,
+                );
+                gl::CopyBufferSubData(gl::COPY_READ_BUFFER, gl::COPY_WRITE_BUFFER, 0, 0, bytes);
+            }
+
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.buffer);
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                (capacity * size_of::<T>()) as GLsizeiptr,
+                std::ptr::null(),
+                self.usage.gl_enum(),
+            );
+
+            if bytes > 0 {
+                gl::BindBuffer(gl::COPY_READ_BUFFER, temporary);
+                gl::CopyBufferSubData(gl::COPY_READ_BUFFER, gl::COPY_WRITE_BUFFER, 0, 0, bytes);
+                gl::DeleteBuffers(1, &temporary);
+            }
+        }
+        self.capacity = capacity;
+    }
+}
+
+impl<T: Pod> Drop for Buffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
@@ -0,0 +1,726 @@
+use crate::context;
+use crate::texture::PixelFormat;
+use gl::types::*;
+
+// S3TC and ASTC come from extensions, the bindings only have core enums
+const COMPRESSED_RGB_S3TC_DXT1: GLenum = 0x83F0;
+const COMPRESSED_RGBA_S3TC_DXT1: GLenum = 0x83F1;
+const COMPRESSED_RGBA_S3TC_DXT3: GLenum = 0x83F2;
+const COMPRESSED_RGBA_S3TC_DXT5: GLenum = 0x83F3;
+const COMPRESSED_SRGB_S3TC_DXT1: GLenum = 0x8C4C;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT1: GLenum = 0x8C4D;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT3: GLenum = 0x8C4E;
+const COMPRESSED_SRGB_ALPHA_S3TC_DXT5: GLenum = 0x8C4F;
+const COMPRESSED_RGBA_ASTC_4X4: GLenum = 0x93B0;
+const COMPRESSED_SRGB8_ALPHA8_ASTC_4X4: GLenum = 0x93D0;
+
+/// ASTC block sizes in the order of their OpenGL enums.
+pub(crate) const ASTC_BLOCKS: [(u8, u8); 14] = [
+    (4, 4),
+    (5, 4),
+    (5, 5),
+    (6, 5),
+    (6, 6),
+    (8, 5),
+    (8, 6),
+    (8, 8),
+    (10, 5),
+    (10, 6),
+    (10, 8),
+    (10, 10),
+    (12, 10),
+    (12, 12),
+];
+
+/// Whether a compressed internal format is decoded from sRGB when sampled.
+pub(crate) fn is_srgb(internal_format: GLenum) -> bool {
+    matches!(
+        internal_format,
+        COMPRESSED_SRGB_S3TC_DXT1
+            ..=COMPRESSED_SRGB_ALPHA_S3TC_DXT5
+                | gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM
+                | gl::COMPRESSED_SRGB8_ETC2
+                | gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2
+                | gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC
+    ) || (COMPRESSED_SRGB8_ALPHA8_ASTC_4X4..COMPRESSED_SRGB8_ALPHA8_ASTC_4X4 + 14)
+        .contains(&internal_format)
+}
+
+/// A block compressed texel format, uploaded as it is when the driver supports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompressedFormat {
+    /// DXT1, `alpha` turns on its one bit alpha
+    Bc1 {
+        alpha: bool,
+    },
+    /// DXT3, explicit four bit alpha
+    Bc2,
+    /// DXT5, interpolated alpha
+    Bc3,
+    /// One channel, also known as RGTC1
+    Bc4 {
+        signed: bool,
+    },
+    /// Two channels, also known as RGTC2, usually a normal map
+    Bc5 {
+        signed: bool,
+    },
+    /// HDR colour
+    Bc6h {
+        signed: bool,
+    },
+    Bc7,
+    Etc2Rgb8,
+    /// ETC2 colour with one bit alpha
+    Etc2Rgb8A1,
+    /// ETC2 colour with EAC alpha
+    Etc2Rgba8,
+    EacR11 {
+        signed: bool,
+    },
+    EacRg11 {
+        signed: bool,
+    },
+    /// ASTC LDR, with the block size in texels
+    Astc {
+        block_width: u8,
+        block_height: u8,
+    },
+}
+
+impl CompressedFormat {
+    /// Width and height of a block in texels.
+    pub fn block_size(self) -> (u32, u32) {
+        match self {
+            CompressedFormat::Astc {
+                block_width,
+                block_height,
+            } => (block_width as u32, block_height as u32),
+            _ => (4, 4),
+        }
+    }
+
+    /// Bytes in one block.
+    pub fn block_bytes(self) -> usize {
+        match self {
+            CompressedFormat::Bc1 { .. }
+            | CompressedFormat::Bc4 { .. }
+            | CompressedFormat::Etc2Rgb8
+            | CompressedFormat::Etc2Rgb8A1
+            | CompressedFormat::EacR11 { .. } => 8,
+            _ => 16,
+        }
+    }
+
+    /// Bytes of one `width` by `height` image, partial blocks at the edges included.
+    pub fn image_size(self, width: u32, height: u32) -> usize {
+        let (block_width, block_height) = self.block_size();
+        let blocks = width.div_ceil(block_width) as usize * height.div_ceil(block_height) as usize;
+        blocks * self.block_bytes()
+    }
+
+    /// The OpenGL internal format, or `None` for combinations that do not exist such as an
+    /// sRGB BC4.
+    pub fn gl_internal_format(self, srgb: bool) -> Option<GLenum> {
+        let format = match (self, srgb) {
+            (CompressedFormat::Bc1 { alpha: false }, false) => COMPRESSED_RGB_S3TC_DXT1,
+            (CompressedFormat::Bc1 { alpha: false }, true) => COMPRESSED_SRGB_S3TC_DXT1,
+            (CompressedFormat::Bc1 { alpha: true }, false) => COMPRESSED_RGBA_S3TC_DXT1,
+            (CompressedFormat::Bc1 { alpha: true }, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT1,
+            (CompressedFormat::Bc2, false) => COMPRESSED_RGBA_S3TC_DXT3,
+            (CompressedFormat::Bc2, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT3,
+            (CompressedFormat::Bc3, false) => COMPRESSED_RGBA_S3TC_DXT5,
+            (CompressedFormat::Bc3, true) => COMPRESSED_SRGB_ALPHA_S3TC_DXT5,
+            (CompressedFormat::Bc4 { signed: false }, false) => gl::COMPRESSED_RED_RGTC1,
+            (CompressedFormat::Bc4 { signed: true }, false) => gl::COMPRESSED_SIGNED_RED_RGTC1,
+            (CompressedFormat::Bc5 { signed: false }, false) => gl::COMPRESSED_RG_RGTC2,
+            (CompressedFormat::Bc5 { signed: true }, false) => gl::COMPRESSED_SIGNED_RG_RGTC2,
+            (CompressedFormat::Bc6h { signed: false }, false) => {
+                gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT
+            }
+            (CompressedFormat::Bc6h { signed: true }, false) => {
+                gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT
+            }
+            (CompressedFormat::Bc7, false) => gl::COMPRESSED_RGBA_BPTC_UNORM,
+            (CompressedFormat::Bc7, true) => gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+            (CompressedFormat::Etc2Rgb8, false) => gl::COMPRESSED_RGB8_ETC2,
+            (CompressedFormat::Etc2Rgb8, true) => gl::COMPRESSED_SRGB8_ETC2,
+            (CompressedFormat::Etc2Rgb8A1, false) => gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (CompressedFormat::Etc2Rgb8A1, true) => gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2,
+            (CompressedFormat::Etc2Rgba8, false) => gl::COMPRESSED_RGBA8_ETC2_EAC,
+            (CompressedFormat::Etc2Rgba8, true) => gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
+            (CompressedFormat::EacR11 { signed: false }, false) => gl::COMPRESSED_R11_EAC,
+            (CompressedFormat::EacR11 { signed: true }, false) => gl::COMPRESSED_SIGNED_R11_EAC,
+            (CompressedFormat::EacRg11 { signed: false }, false) => gl::COMPRESSED_RG11_EAC,
+            (CompressedFormat::EacRg11 { signed: true }, false) => gl::COMPRESSED_SIGNED_RG11_EAC,
+            (
+                CompressedFormat::Astc {
+                    block_width,
+                    block_height,
+                },
+                srgb,
+            ) => {
+                let index = ASTC_BLOCKS
+                    .iter()
+                    .position(|&block| block == (block_width, block_height))?;
+                let first = match srgb {
+                    true => COMPRESSED_SRGB8_ALPHA8_ASTC_4X4,
+                    false => COMPRESSED_RGBA_ASTC_4X4,
+                };
+                first + index as GLenum
+            }
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    /// Whether the current context can sample this format without decompressing it.
+    pub fn is_supported(self) -> bool {
+        match self {
+            CompressedFormat::Bc1 { .. } | CompressedFormat::Bc2 | CompressedFormat::Bc3 => {
+                context::has_extension("GL_EXT_texture_compression_s3tc")
+            }
+            CompressedFormat::Bc4 { .. } | CompressedFormat::Bc5 { .. } => {
+                context::supports_version(3, 0)
+                    || context::has_extension("GL_ARB_texture_compression_rgtc")
+            }
+            CompressedFormat::Bc6h { .. } | CompressedFormat::Bc7 => {
+                context::supports_version(4, 2)
+                    || context::has_extension("GL_ARB_texture_compression_bptc")
+            }
+            CompressedFormat::Etc2Rgb8
+            | CompressedFormat::Etc2Rgb8A1
+            | CompressedFormat::Etc2Rgba8
+            | CompressedFormat::EacR11 { .. }
+            | CompressedFormat::EacRg11 { .. } => {
+                context::supports_version(4, 3)
+                    || context::has_extension("GL_ARB_ES3_compatibility")
+            }
+            CompressedFormat::Astc { .. } => {
+                context::has_extension("GL_KHR_texture_compression_astc_ldr")
+            }
+        }
+    }
+
+    /// Decodes one `width` by `height` image on the CPU, for drivers without the format.
+    ///
+    /// BC1 to BC5, ETC2 and EAC can be decoded. BC6H, BC7 and ASTC return `None` and need
+    /// driver support.
+    pub fn decompress(
+        self,
+        srgb: bool,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Option<(PixelFormat, Vec<u8>)> {
+        if data.len() < self.image_size(width, height) {
+            return None;
+        }
+
+        let rgba8 = match srgb {
+            true => PixelFormat::new(gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+            false => PixelFormat::new(gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        };
+        let decoded = match self {
+            CompressedFormat::Bc1 { alpha } => (
+                rgba8,
+                decode_blocks(width, height, data, 8, |block| {
+                    decode_bc1(block, false, alpha)
+                }),
+            ),
+            CompressedFormat::Bc2 => (rgba8, decode_blocks(width, height, data, 16, decode_bc2)),
+            CompressedFormat::Bc3 => (rgba8, decode_blocks(width, height, data, 16, decode_bc3)),
+            CompressedFormat::Bc4 { signed } => (
+                match signed {
+                    true => PixelFormat::new(gl::R8_SNORM, gl::RED, gl::BYTE),
+                    false => PixelFormat::new(gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+                },
+                decode_blocks(width, height, data, 8, |block| {
+                    decode_bc4(block, signed).map(|value| [value])
+                }),
+            ),
+            CompressedFormat::Bc5 { signed } => (
+                match signed {
+                    true => PixelFormat::new(gl::RG8_SNORM, gl::RG, gl::BYTE),
+                    false => PixelFormat::new(gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+                },
+                decode_blocks(width, height, data, 16, |block| {
+                    let (red, green) = (decode_bc4(block, signed), decode_bc4(&block[8..], signed));
+                    std::array::from_fn(|texel| [red[texel], green[texel]])
+                }),
+            ),
+            CompressedFormat::Etc2Rgb8 => (
+                rgba8,
+                decode_blocks(width, height, data, 8, |block| decode_etc2(block, false)),
+            ),
+            CompressedFormat::Etc2Rgb8A1 => (
+                rgba8,
+                decode_blocks(width, height, data, 8, |block| decode_etc2(block, true)),
+            ),
+            CompressedFormat::Etc2Rgba8 => (
+                rgba8,
+                decode_blocks(width, height, data, 16, |block| {
+                    let alpha = decode_eac8(block);
+                    let mut texels = decode_etc2(&block[8..], false);
+                    for (texel, alpha) in texels.iter_mut().zip(alpha) {
+                        texel[3] = alpha;
+                    }
+                    texels
+                }),
+            ),
+            CompressedFormat::EacR11 { signed } => (
+                match signed {
+                    true => PixelFormat::new(gl::R16_SNORM, gl::RED, gl::SHORT),
+                    false => PixelFormat::new(gl::R16, gl::RED, gl::UNSIGNED_SHORT),
+                },
+                decode_blocks(width, height, data, 8, |block| {
+                    decode_eac11(block, signed).map(u16::to_ne_bytes)
+                }),
+            ),
+            CompressedFormat::EacRg11 { signed } => (
+                match signed {
+                    true => PixelFormat::new(gl::RG16_SNORM, gl::RG, gl::SHORT),
+                    false => PixelFormat::new(gl::RG16, gl::RG, gl::UNSIGNED_SHORT),
+                },
+                decode_blocks(width, height, data, 16, |block| {
+                    let red = decode_eac11(block, signed);
+                    let green = decode_eac11(&block[8..], signed);
+                    std::array::from_fn(|texel| {
+                        let [r0, r1] = red[texel].to_ne_bytes();
+                        let [g0, g1] = green[texel].to_ne_bytes();
+                        [r0, r1, g0, g1]
+                    })
+                }),
+            ),
+            CompressedFormat::Bc6h { .. }
+            | CompressedFormat::Bc7
+            | CompressedFormat::Astc { .. } => return None,
+        };
+        Some(decoded)
+    }
+}
+
+// Decode every 4x4 block of an image into tightly packed rows of N byte texels, dropping
+// the texels of edge blocks that hang over the image
+fn decode_blocks<const N: usize>(
+    width: u32,
+    height: u32,
+    data: &[u8],
+    block_bytes: usize,
+    decode: impl Fn(&[u8]) -> [[u8; N]; 16],
+) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let blocks_across = width.div_ceil(4);
+    let mut pixels = vec![0; width * height * N];
+    for (index, block) in data
+        .chunks_exact(block_bytes)
+        .take(blocks_across * height.div_ceil(4))
+        .enumerate()
+    {
+        let texels = decode(block);
+        let (block_x, block_y) = (index % blocks_across * 4, index / blocks_across * 4);
+        for (texel, value) in texels.iter().enumerate() {
+            let (x, y) = (block_x + texel % 4, block_y + texel / 4);
+            if x < width && y < height {
+                let offset = (y * width + x) * N;
+                pixels[offset..offset + N].copy_from_slice(value);
+            }
+        }
+    }
+    pixels
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let (r, g, b) = ((color >> 11) & 31, (color >> 5) & 63, color & 31);
+    [
+        (r << 3 | r >> 2) as u8,
+        (g << 2 | g >> 4) as u8,
+        (b << 3 | b >> 2) as u8,
+        255,
+    ]
+}
+
+// Weighted average of two colours
+fn blend(a: [u8; 4], b: [u8; 4], weight_a: u32, weight_b: u32) -> [u8; 4] {
+    std::array::from_fn(|channel| {
+        ((a[channel] as u32 * weight_a + b[channel] as u32 * weight_b) / (weight_a + weight_b))
+            as u8
+    })
+}
+
+// The colour half of BC1, BC2 and BC3 blocks. The latter two always use four colours, BC1
+// switches to three colours and transparent black when the endpoints are in reverse order
+fn decode_bc1(block: &[u8], four_colors: bool, alpha: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(color0), rgb565(color1));
+    let palette = match four_colors || color0 > color1 {
+        true => [a, b, blend(a, b, 2, 1), blend(a, b, 1, 2)],
+        false => [
+            a,
+            b,
+            blend(a, b, 1, 1),
+            [0, 0, 0, if alpha { 0 } else { 255 }],
+        ],
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|texel| palette[(indices >> (2 * texel)) as usize & 3])
+}
+
+fn decode_bc2(block: &[u8]) -> [[u8; 4]; 16] {
+    let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+    let mut texels = decode_bc1(&block[8..], true, false);
+    for (texel, color) in texels.iter_mut().enumerate() {
+        color[3] = ((alpha >> (4 * texel)) & 15) as u8 * 17;
+    }
+    texels
+}
+
+fn decode_bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let alpha = decode_bc4(block, false);
+    let mut texels = decode_bc1(&block[8..], true, false);
+    for (color, alpha) in texels.iter_mut().zip(alpha) {
+        color[3] = alpha;
+    }
+    texels
+}
+
+// One interpolated channel, as BC4 and the alpha of BC3. Signed values come back as the
+// bits of an i8
+fn decode_bc4(block: &[u8], signed: bool) -> [u8; 16] {
+    let (a, b, low, high) = match signed {
+        true => (
+            (block[0] as i8).max(-127) as i32,
+            (block[1] as i8).max(-127) as i32,
+            -127,
+            127,
+        ),
+        false => (block[0] as i32, block[1] as i32, 0, 255),
+    };
+    let mut palette = [a, b, 0, 0, 0, 0, 0, 0];
+    if a > b {
+        for step in 1..7 {
+            palette[step + 1] = (a * (7 - step as i32) + b * step as i32) / 7;
+        }
+    } else {
+        for step in 1..5 {
+            palette[step + 1] = (a * (5 - step as i32) + b * step as i32) / 5;
+        }
+        palette[6] = low;
+        palette[7] = high;
+    }
+
+    let mut bits = [0; 8];
+    bits[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(bits);
+    std::array::from_fn(|texel| palette[(indices >> (3 * texel)) as usize & 7] as u8)
+}
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn clamp_color(color: [i32; 3]) -> [u8; 4] {
+    let [r, g, b] = color.map(|channel| channel.clamp(0, 255) as u8);
+    [r, g, b, 255]
+}
+
+fn offset_color(color: [i32; 3], offset: i32) -> [u8; 4] {
+    clamp_color(color.map(|channel| channel + offset))
+}
+
+// ETC2 colour blocks, which extend ETC1 with the T, H and planar modes hidden in what would
+// be overflowing differential colours. With `punchthrough` the differential bit instead
+// says whether the block is opaque
+fn decode_etc2(block: &[u8], punchthrough: bool) -> [[u8; 4]; 16] {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let field = |shift: u32, len: u32| ((bits >> shift) & ((1 << len) - 1)) as i32;
+    let extend4 = |value: i32| value * 17;
+    let extend5 = |value: i32| value << 3 | value >> 2;
+    let signed3 = |value: i32| if value >= 4 { value - 8 } else { value };
+
+    let differential = field(33, 1) == 1;
+    let opaque = !punchthrough || differential;
+    // Pixel indices are stored column by column, their high bits above the low bits
+    let index = |texel: usize| {
+        let i = texel % 4 * 4 + texel / 4;
+        ((bits >> (16 + i)) & 1) << 1 | (bits >> i) & 1
+    };
+    let paint = |colors: [[u8; 4]; 4]| -> [[u8; 4]; 16] {
+        std::array::from_fn(|texel| match index(texel) {
+            2 if !opaque => [0; 4],
+            index => colors[index as usize],
+        })
+    };
+
+    let bases = if differential || punchthrough {
+        let (r, g, b) = (field(59, 5), field(51, 5), field(43, 5));
+        let (dr, dg, db) = (
+            signed3(field(56, 3)),
+            signed3(field(48, 3)),
+            signed3(field(40, 3)),
+        );
+        if !(0..32).contains(&(r + dr)) {
+            // T mode
+            let color1 = [
+                (field(59, 2) << 2 | field(56, 2)),
+                field(52, 4),
+                field(48, 4),
+            ];
+            let color2 = [field(44, 4), field(40, 4), field(36, 4)];
+            let distance = ETC_DISTANCES[(field(34, 2) << 1 | field(32, 1)) as usize];
+            let (color1, color2) = (color1.map(extend4), color2.map(extend4));
+            return paint([
+                clamp_color(color1),
+                offset_color(color2, distance),
+                clamp_color(color2),
+                offset_color(color2, -distance),
+            ]);
+        }
+        if !(0..32).contains(&(g + dg)) {
+            // H mode, the order of the two colours holds the last distance bit
+            let color1 = [
+                field(59, 4),
+                field(56, 3) << 1 | field(52, 1),
+                field(51, 1) << 3 | field(47, 3),
+            ];
+            let color2 = [field(43, 4), field(39, 4), field(35, 4)];
+            let packed = |[r, g, b]: [i32; 3]| r << 8 | g << 4 | b;
+            let order = (packed(color1) >= packed(color2)) as i32;
+            let distance = ETC_DISTANCES[(field(34, 1) << 2 | field(32, 1) << 1 | order) as usize];
+            let (color1, color2) = (color1.map(extend4), color2.map(extend4));
+            return paint([
+                offset_color(color1, distance),
+                offset_color(color1, -distance),
+                offset_color(color2, distance),
+                offset_color(color2, -distance),
+            ]);
+        }
+        if !(0..32).contains(&(b + db)) {
+            return decode_etc2_planar(&field);
+        }
+        [
+            [r, g, b].map(extend5),
+            [r + dr, g + dg, b + db].map(extend5),
+        ]
+    } else {
+        [
+            [field(60, 4), field(52, 4), field(44, 4)].map(extend4),
+            [field(56, 4), field(48, 4), field(40, 4)].map(extend4),
+        ]
+    };
+
+    // Two subblocks side by side, or stacked when flipped, each with its own base colour
+    // and modifier table
+    let flip = field(32, 1) == 1;
+    let tables = [field(37, 3) as usize, field(34, 3) as usize];
+    std::array::from_fn(|texel| {
+        let (x, y) = (texel % 4, texel / 4);
+        let subblock = if flip { y / 2 } else { x / 2 };
+        let [small, large] = ETC_MODIFIERS[tables[subblock]];
+        let modifier = match (index(texel), opaque) {
+            (0, true) => small,
+            (0, false) => 0,
+            (1, _) => large,
+            (2, true) => -small,
+            (2, false) => return [0; 4],
+            _ => -large,
+        };
+        offset_color(bases[subblock], modifier)
+    })
+}
+
+// A colour gradient given by the colours at the origin, at the right and at the bottom
+fn decode_etc2_planar(field: &impl Fn(u32, u32) -> i32) -> [[u8; 4]; 16] {
+    let extend6 = |value: i32| value << 2 | value >> 4;
+    let extend7 = |value: i32| value << 1 | value >> 6;
+    let origin = [
+        extend6(field(57, 6)),
+        extend7(field(56, 1) << 6 | field(49, 6)),
+        extend6(field(48, 1) << 5 | field(43, 2) << 3 | field(39, 3)),
+    ];
+    let horizontal = [
+        extend6(field(34, 5) << 1 | field(32, 1)),
+        extend7(field(25, 7)),
+        extend6(field(19, 6)),
+    ];
+    let vertical = [
+        extend6(field(13, 6)),
+        extend7(field(6, 7)),
+        extend6(field(0, 6)),
+    ];
+
+    std::array::from_fn(|texel| {
+        let (x, y) = ((texel % 4) as i32, (texel / 4) as i32);
+        clamp_color(std::array::from_fn(|channel| {
+            let (o, h, v) = (origin[channel], horizontal[channel], vertical[channel]);
+            (x * (h - o) + y * (v - o) + 4 * o + 2) >> 2
+        }))
+    })
+}
+
+// The base, multiplier, modifier table and column major 3 bit indices shared by EAC blocks
+fn eac_fields(block: &[u8]) -> (u8, i32, [i32; 16]) {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let multiplier = (block[1] >> 4) as i32;
+    let table = EAC_MODIFIERS[(block[1] & 15) as usize];
+    let modifiers = std::array::from_fn(|texel| {
+        let i = texel % 4 * 4 + texel / 4;
+        table[(bits >> (45 - 3 * i)) as usize & 7]
+    });
+    (block[0], multiplier, modifiers)
+}
+
+// The 8 bit alpha of ETC2 RGBA8
+fn decode_eac8(block: &[u8]) -> [u8; 16] {
+    let (base, multiplier, modifiers) = eac_fields(block);
+    modifiers.map(|modifier| (base as i32 + modifier * multiplier).clamp(0, 255) as u8)
+}
+
+// One 11 bit EAC channel widened to 16 bits, signed values come back as the bits of an i16
+fn decode_eac11(block: &[u8], signed: bool) -> [u16; 16] {
+    let (base, multiplier, modifiers) = eac_fields(block);
+    let scale = |modifier: i32| match multiplier {
+        0 => modifier,
+        _ => modifier * multiplier * 8,
+    };
+    match signed {
+        true => {
+            let base = (base as i8).max(-127) as i32 * 8;
+            modifiers.map(|modifier| {
+                let value = (base + scale(modifier)).clamp(-1023, 1023);
+                let magnitude = value.abs() * 32 + value.abs() / 32;
+                (magnitude * value.signum()) as i16 as u16
+            })
+        }
+        false => {
+            let base = base as i32 * 8 + 4;
+            modifiers.map(|modifier| {
+                let value = (base + scale(modifier)).clamp(0, 2047);
+                (value << 5 | value >> 6) as u16
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn sizes_round_up_to_whole_blocks() {
+        let bc1 = CompressedFormat::Bc1 { alpha: false };
+        assert_eq!(bc1.image_size(4, 4), 8);
+        assert_eq!(bc1.image_size(5, 5), 4 * 8);
+        assert_eq!(bc1.image_size(1, 1), 8);
+        assert_eq!(CompressedFormat::Bc3.image_size(8, 4), 2 * 16);
+        assert_eq!(CompressedFormat::Etc2Rgba8.image_size(6, 9), 2 * 3 * 16);
+    }
+
+    #[test]
+    fn decodes_four_color_bc1() {
+        // Red then blue endpoints, the first row using every palette entry in turn
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0b11_10_01_00, 0, 0, 0];
+        let texels = decode_bc1(&block, false, false);
+        assert_eq!(
+            texels[..4],
+            [RED, BLUE, [170, 0, 85, 255], [85, 0, 170, 255]]
+        );
+        assert!(texels[4..].iter().all(|&texel| texel == RED));
+    }
+
+    #[test]
+    fn decodes_three_color_bc1_with_alpha() {
+        // Endpoints in reverse order switch to three colours and transparent black
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0b11_10_01_00, 0, 0, 0];
+        let (format, pixels) = CompressedFormat::Bc1 { alpha: true }
+            .decompress(false, 4, 4, &block)
+            .unwrap();
+        assert_eq!(
+            format,
+            PixelFormat::new(gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE)
+        );
+        assert_eq!(
+            pixels[..16],
+            [BLUE, RED, [127, 0, 127, 255], [0, 0, 0, 0]].concat()
+        );
+
+        // Without alpha the fourth colour is opaque black
+        let (_, pixels) = CompressedFormat::Bc1 { alpha: false }
+            .decompress(false, 4, 4, &block)
+            .unwrap();
+        assert_eq!(pixels[12..16], [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn crops_edge_blocks() {
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0b0000_0100, 0b0000_0001, 0, 0];
+        let (_, pixels) = CompressedFormat::Bc1 { alpha: false }
+            .decompress(false, 2, 2, &block)
+            .unwrap();
+        assert_eq!(pixels, [RED, BLUE, BLUE, RED].concat());
+        assert!(CompressedFormat::Bc1 { alpha: false }
+            .decompress(false, 8, 4, &block)
+            .is_none());
+    }
+
+    #[test]
+    fn decodes_individual_etc2() {
+        // Bases 0x88 on the left and 0x44 on the right, modifier tables 0 and 7. The
+        // first row picks +small, +large, -small and -large, the last texel +large
+        let block = [0x84, 0x84, 0x84, 7 << 2, 0x11, 0x00, 0x90, 0x10];
+        let texels = decode_etc2(&block, false);
+        let gray = |value: u8| [value, value, value, 255];
+        assert_eq!(texels[..4], [gray(138), gray(144), gray(21), gray(0)]);
+        assert_eq!(texels[15], gray(251));
+        for y in 1..4 {
+            assert_eq!(texels[y * 4..y * 4 + 2], [gray(138); 2]);
+        }
+        assert_eq!(texels[4 + 2..4 + 4], [gray(115); 2]);
+    }
+
+    #[test]
+    fn decodes_flipped_differential_etc2() {
+        // A base of 16 and a delta of -1 on every channel, stacked subblocks with
+        // modifier table 1, every texel at +small
+        let block = [0x87, 0x87, 0x87, 1 << 5 | 1 << 2 | 1 << 1 | 1, 0, 0, 0, 0];
+        let (_, pixels) = CompressedFormat::Etc2Rgb8
+            .decompress(true, 4, 4, &block)
+            .unwrap();
+        let (top, bottom) = pixels.split_at(32);
+        assert!(top.chunks(4).all(|texel| texel == [137, 137, 137, 255]));
+        assert!(bottom.chunks(4).all(|texel| texel == [128, 128, 128, 255]));
+    }
+}
@@ -0,0 +1,109 @@
+use crate::error::ShaderError;
+use crate::preprocess::{Defines, ShaderSource};
+use crate::shader::{Shader, ShaderStage};
+use gl::types::*;
+use std::ffi::{CStr, CString};
+use std::ops::BitOr;
+use std::path::Path;
+
+/// A program made of a single compute stage, needs an OpenGL 4.3 context.
+pub struct ComputeProgram {
+    shader: Shader,
+}
+
+impl ComputeProgram {
+    pub fn from_cstr(source: &CStr) -> Result<ComputeProgram, ShaderError> {
+        ShaderStage::Compute.check_supported()?;
+        let shader = Shader::link(&[(ShaderStage::Compute, source)])?;
+        Ok(ComputeProgram { shader })
+    }
+
+    pub fn from_file(path: &Path) -> Result<ComputeProgram, ShaderError> {
+        ComputeProgram::from_file_with_defines(path, &Defines::new())
+    }
+
+    pub fn from_file_with_defines(
+        path: &Path,
+        defines: &Defines,
+    ) -> Result<ComputeProgram, ShaderError> {
+        let source = CString::new(ShaderSource::from_file(path, defines)?.source)?;
+        ComputeProgram::from_cstr(&source)
+    }
+
+    /// The program, for setting uniforms and introspection.
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    pub fn get(&self) -> u32 {
+        self.shader.get()
+    }
+
+    /// The `local_size_x/y/z` declared in the shader.
+    pub fn work_group_size(&self) -> [u32; 3] {
+        let mut size = [0; 3];
+        unsafe {
+            gl::GetProgramiv(
+                self.shader.get(),
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                size.as_mut_ptr(),
+            );
+        }
+        size.map(|size| size as u32)
+    }
+
+    /// Runs `x * y * z` work groups, leaving the program in use.
+    ///
+    /// The writes are not visible to later commands until a `memory_barrier` for the way
+    /// they will be read.
+    pub fn dispatch(&self, x: u32, y: u32, z: u32) {
+        unsafe {
+            gl::UseProgram(self.shader.get());
+            gl::DispatchCompute(x, y, z);
+        }
+    }
+
+    /// Enough work groups to cover `width * height * depth` invocations.
+    pub fn dispatch_size(&self, width: u32, height: u32, depth: u32) {
+        let [x, y, z] = self.work_group_size();
+        self.dispatch(
+            width.div_ceil(x.max(1)),
+            height.div_ceil(y.max(1)),
+            depth.div_ceil(z.max(1)),
+        );
+    }
+}
+
+/// Which kinds of reads `memory_barrier` makes shader writes visible to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Barrier(pub GLbitfield);
+
+impl Barrier {
+    pub const VERTEX_ATTRIB_ARRAY: Barrier = Barrier(gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT);
+    pub const ELEMENT_ARRAY: Barrier = Barrier(gl::ELEMENT_ARRAY_BARRIER_BIT);
+    pub const UNIFORM: Barrier = Barrier(gl::UNIFORM_BARRIER_BIT);
+    pub const TEXTURE_FETCH: Barrier = Barrier(gl::TEXTURE_FETCH_BARRIER_BIT);
+    pub const SHADER_IMAGE_ACCESS: Barrier = Barrier(gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    pub const COMMAND: Barrier = Barrier(gl::COMMAND_BARRIER_BIT);
+    pub const PIXEL_BUFFER: Barrier = Barrier(gl::PIXEL_BUFFER_BARRIER_BIT);
+    pub const TEXTURE_UPDATE: Barrier = Barrier(gl::TEXTURE_UPDATE_BARRIER_BIT);
+    pub const BUFFER_UPDATE: Barrier = Barrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+    pub const FRAMEBUFFER: Barrier = Barrier(gl::FRAMEBUFFER_BARRIER_BIT);
+    pub const ATOMIC_COUNTER: Barrier = Barrier(gl::ATOMIC_COUNTER_BARRIER_BIT);
+    pub const SHADER_STORAGE: Barrier = Barrier(gl::SHADER_STORAGE_BARRIER_BIT);
+    pub const ALL: Barrier = Barrier(gl::ALL_BARRIER_BITS);
+}
+
+impl BitOr for Barrier {
+    type Output = Barrier;
+
+    fn bitor(self, other: Barrier) -> Barrier {
+        Barrier(self.0 | other.0)
+    }
+}
+
+pub fn memory_barrier(barrier: Barrier) {
+    unsafe {
+        gl::MemoryBarrier(barrier.0);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::compressed::{CompressedFormat, ASTC_BLOCKS};
+use crate::error::TextureError;
+use crate::texture::PixelFormat;
+use ddsfile::{Caps2, D3DFormat, Dds, DxgiFormat, MiscFlag};
+use ktx2::{Format, SupercompressionScheme};
+use std::path::Path;
+
+const KTX2_MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+const DDS_MAGIC: [u8; 4] = *b"DDS ";
+
+/// How the texels of `TextureData` are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Uncompressed(PixelFormat),
+    Compressed {
+        format: CompressedFormat,
+        srgb: bool,
+    },
+}
+
+impl DataFormat {
+    /// Bytes of one `width` by `height` image.
+    pub fn image_size(&self, width: u32, height: u32) -> usize {
+        match self {
+            DataFormat::Uncompressed(format) => {
+                width as usize * height as usize * format.bytes_per_pixel()
+            }
+            DataFormat::Compressed { format, .. } => format.image_size(width, height),
+        }
+    }
+}
+
+/// Every mip level of a texture loaded from a KTX2 or DDS file, ready to upload with
+/// `Texture2D::from_data` and the like.
+#[derive(Debug, Clone)]
+pub struct TextureData {
+    pub width: u32,
+    pub height: u32,
+    /// 1 unless the texture is 3D
+    pub depth: u32,
+    /// 1 unless the texture is an array
+    pub layers: u32,
+    /// 6 for cubemaps, 1 otherwise
+    pub faces: u32,
+    pub format: DataFormat,
+    /// Level 0 first, each holding its layers one after another, each layer its faces and
+    /// each face its depth slices
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl TextureData {
+    /// Loads a KTX2 or DDS file, told apart by their first bytes.
+    pub fn from_file(path: &Path) -> Result<TextureData, TextureError> {
+        let bytes = std::fs::read(path).map_err(|error| {
+            TextureError::Container(format!("cannot read {}: {error}", path.display()))
+        })?;
+        TextureData::from_bytes(&bytes)
+    }
+
+    /// Parses a KTX2 or DDS file held in memory.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TextureData, TextureError> {
+        if bytes.starts_with(&KTX2_MAGIC) {
+            TextureData::from_ktx2(bytes)
+        } else if bytes.starts_with(&DDS_MAGIC) {
+            TextureData::from_dds(bytes)
+        } else {
+            Err(TextureError::Container(
+                "not a KTX2 or DDS file".to_string(),
+            ))
+        }
+    }
+
+    pub fn from_ktx2(bytes: &[u8]) -> Result<TextureData, TextureError> {
+        let reader = ktx2::Reader::new(bytes)
+            .map_err(|error| TextureError::Container(format!("invalid KTX2 file: {error}")))?;
+        let header = reader.header();
+        if let Some(scheme) = header.supercompression_scheme {
+            return Err(TextureError::UnsupportedFormat(match scheme {
+                SupercompressionScheme::BasisLZ => "Basis Universal KTX2".to_string(),
+                scheme => format!("{scheme:?} supercompressed KTX2"),
+            }));
+        }
+        let format = match header.format {
+            Some(format) => ktx2_format(format)?,
+            None => {
+                return Err(TextureError::UnsupportedFormat(
+                    "KTX2 without a Vulkan format".to_string(),
+                ))
+            }
+        };
+
+        // Zero marks a dimension the texture does not have
+        let data = TextureData {
+            width: header.pixel_width,
+            height: header.pixel_height.max(1),
+            depth: header.pixel_depth.max(1),
+            layers: header.layer_count.max(1),
+            faces: header.face_count,
+            format,
+            levels: reader.levels().map(|level| level.data.to_vec()).collect(),
+        };
+        data.check_sizes()?;
+        Ok(data)
+    }
+
+    pub fn from_dds(bytes: &[u8]) -> Result<TextureData, TextureError> {
+        let dds = Dds::read(bytes)
+            .map_err(|error| TextureError::Container(format!("invalid DDS file: {error}")))?;
+        let format = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+            (Some(format), _) => dxgi_format(format)?,
+            (None, Some(format)) => d3d_format(format)?,
+            (None, None) => {
+                return Err(TextureError::UnsupportedFormat(
+                    "DDS with an unknown pixel format".to_string(),
+                ))
+            }
+        };
+
+        let cubemap = match &dds.header10 {
+            Some(header10) => header10.misc_flag.contains(MiscFlag::TEXTURECUBE),
+            None => dds.header.caps2.contains(Caps2::CUBEMAP),
+        };
+        let (layers, faces) = match (&dds.header10, cubemap) {
+            (Some(header10), true) => (header10.array_size.max(1), 6),
+            (Some(header10), false) => (header10.array_size.max(1), 1),
+            (None, true) => (1, 6),
+            (None, false) => (1, 1),
+        };
+        let mut data = TextureData {
+            width: dds.get_width(),
+            height: dds.get_height(),
+            depth: dds.get_depth(),
+            layers,
+            faces,
+            format,
+            levels: Vec::new(),
+        };
+
+        // DDS stores each face with its whole mip chain, regroup the images by level
+        let level_count = dds.get_num_mipmap_levels().max(1) as usize;
+        let images = (layers * faces) as usize;
+        let image_sizes: Vec<usize> = (0..level_count)
+            .map(|level| {
+                let (width, height, depth) = data.level_size(level);
+                data.format.image_size(width, height) * depth as usize
+            })
+            .collect();
+        let expected = image_sizes.iter().sum::<usize>() * images;
+        if dds.data.len() < expected {
+            return Err(TextureError::DataSize {
+                expected,
+                found: dds.data.len(),
+            });
+        }
+
+        data.levels = image_sizes
+            .iter()
+            .map(|&size| Vec::with_capacity(size * images))
+            .collect();
+        let mut offset = 0;
+        for _ in 0..images {
+            for (level, &size) in data.levels.iter_mut().zip(&image_sizes) {
+                level.extend_from_slice(&dds.data[offset..offset + size]);
+                offset += size;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Width, height and depth of mip `level`.
+    pub fn level_size(&self, level: usize) -> (u32, u32, u32) {
+        let shrink = |size: u32| (size >> level).max(1);
+        (shrink(self.width), shrink(self.height), shrink(self.depth))
+    }
+
+    pub fn is_cubemap(&self) -> bool {
+        self.faces == 6
+    }
+
+    // Every level has to hold all its images, or uploading would read past the end
+    pub(crate) fn check_sizes(&self) -> Result<(), TextureError> {
+        if self.levels.is_empty() {
+            return Err(TextureError::Empty);
+        }
+        for (index, level) in self.levels.iter().enumerate() {
+            let (width, height, depth) = self.level_size(index);
+            let expected =
+                self.format.image_size(width, height) * (depth * self.layers * self.faces) as usize;
+            if level.len() < expected {
+                return Err(TextureError::DataSize {
+                    expected,
+                    found: level.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn uncompressed(internal_format: u32, format: u32, pixel_type: u32) -> DataFormat {
+    DataFormat::Uncompressed(PixelFormat::new(internal_format, format, pixel_type))
+}
+
+fn compressed(format: CompressedFormat, srgb: bool) -> DataFormat {
+    DataFormat::Compressed { format, srgb }
+}
+
+fn ktx2_format(format: Format) -> Result<DataFormat, TextureError> {
+    let data_format = match format {
+        Format::R8_UNORM => uncompressed(gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+        Format::R8G8_UNORM => uncompressed(gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+        Format::R8G8B8_UNORM => uncompressed(gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
+        Format::R8G8B8_SRGB => uncompressed(gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE),
+        Format::R8G8B8A8_UNORM => uncompressed(gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        Format::R8G8B8A8_SRGB => uncompressed(gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        Format::B8G8R8A8_UNORM => uncompressed(gl::RGBA8, gl::BGRA, gl::UNSIGNED_BYTE),
+        Format::B8G8R8A8_SRGB => uncompressed(gl::SRGB8_ALPHA8, gl::BGRA, gl::UNSIGNED_BYTE),
+        Format::R16_UNORM => uncompressed(gl::R16, gl::RED, gl::UNSIGNED_SHORT),
+        Format::R16G16_UNORM => uncompressed(gl::RG16, gl::RG, gl::UNSIGNED_SHORT),
+        Format::R16G16B16A16_UNORM => uncompressed(gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+        Format::R16_SFLOAT => uncompressed(gl::R16F, gl::RED, gl::HALF_FLOAT),
+        Format::R16G16_SFLOAT => uncompressed(gl::RG16F, gl::RG, gl::HALF_FLOAT),
+        Format::R16G16B16A16_SFLOAT => uncompressed(gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        Format::R32_SFLOAT => uncompressed(gl::R32F, gl::RED, gl::FLOAT),
+        Format::R32G32_SFLOAT => uncompressed(gl::RG32F, gl::RG, gl::FLOAT),
+        Format::R32G32B32_SFLOAT => uncompressed(gl::RGB32F, gl::RGB, gl::FLOAT),
+        Format::R32G32B32A32_SFLOAT => uncompressed(gl::RGBA32F, gl::RGBA, gl::FLOAT),
+        Format::B10G11R11_UFLOAT_PACK32 => uncompressed(
+            gl::R11F_G11F_B10F,
+            gl::RGB,
+            gl::UNSIGNED_INT_10F_11F_11F_REV,
+        ),
+        Format::E5B9G9R9_UFLOAT_PACK32 => {
+            uncompressed(gl::RGB9_E5, gl::RGB, gl::UNSIGNED_INT_5_9_9_9_REV)
+        }
+        Format::BC1_RGB_UNORM_BLOCK => compressed(CompressedFormat::Bc1 { alpha: false }, false),
+        Format::BC1_RGB_SRGB_BLOCK => compressed(CompressedFormat::Bc1 { alpha: false }, true),
+        Format::BC1_RGBA_UNORM_BLOCK => compressed(CompressedFormat::Bc1 { alpha: true }, false),
+        Format::BC1_RGBA_SRGB_BLOCK => compressed(CompressedFormat::Bc1 { alpha: true }, true),
+        Format::BC2_UNORM_BLOCK => compressed(CompressedFormat::Bc2, false),
+        Format::BC2_SRGB_BLOCK => compressed(CompressedFormat::Bc2, true),
+        Format::BC3_UNORM_BLOCK => compressed(CompressedFormat::Bc3, false),
+        Format::BC3_SRGB_BLOCK => compressed(CompressedFormat::Bc3, true),
+        Format::BC4_UNORM_BLOCK => compressed(CompressedFormat::Bc4 { signed: false }, false),
+        Format::BC4_SNORM_BLOCK => compressed(CompressedFormat::Bc4 { signed: true }, false),
+        Format::BC5_UNORM_BLOCK => compressed(CompressedFormat::Bc5 { signed: false }, false),
+        Format::BC5_SNORM_BLOCK => compressed(CompressedFormat::Bc5 { signed: true }, false),
+        Format::BC6H_UFLOAT_BLOCK => compressed(CompressedFormat::Bc6h { signed: false }, false),
+        Format::BC6H_SFLOAT_BLOCK => compressed(CompressedFormat::Bc6h { signed: true }, false),
+        Format::BC7_UNORM_BLOCK => compressed(CompressedFormat::Bc7, false),
+        Format::BC7_SRGB_BLOCK => compressed(CompressedFormat::Bc7, true),
+        Format::ETC2_R8G8B8_UNORM_BLOCK => compressed(CompressedFormat::Etc2Rgb8, false),
+        Format::ETC2_R8G8B8_SRGB_BLOCK => compressed(CompressedFormat::Etc2Rgb8, true),
+        Format::ETC2_R8G8B8A1_UNORM_BLOCK => compressed(CompressedFormat::Etc2Rgb8A1, false),
+        Format::ETC2_R8G8B8A1_SRGB_BLOCK => compressed(CompressedFormat::Etc2Rgb8A1, true),
+        Format::ETC2_R8G8B8A8_UNORM_BLOCK => compressed(CompressedFormat::Etc2Rgba8, false),
+        Format::ETC2_R8G8B8A8_SRGB_BLOCK => compressed(CompressedFormat::Etc2Rgba8, true),
+        Format::EAC_R11_UNORM_BLOCK => {
+            compressed(CompressedFormat::EacR11 { signed: false }, false)
+        }
+        Format::EAC_R11_SNORM_BLOCK => compressed(CompressedFormat::EacR11 { signed: true }, false),
+        Format::EAC_R11G11_UNORM_BLOCK => {
+            compressed(CompressedFormat::EacRg11 { signed: false }, false)
+        }
+        Format::EAC_R11G11_SNORM_BLOCK => {
+            compressed(CompressedFormat::EacRg11 { signed: true }, false)
+        }
+        // The LDR ASTC formats alternate UNORM and SRGB, in the same order as OpenGL's
+        format
+            if (Format::ASTC_4x4_UNORM_BLOCK.value()..=Format::ASTC_12x12_SRGB_BLOCK.value())
+                .contains(&format.value()) =>
+        {
+            let index = (format.value() - Format::ASTC_4x4_UNORM_BLOCK.value()) as usize;
+            let (block_width, block_height) = ASTC_BLOCKS[index / 2];
+            compressed(
+                CompressedFormat::Astc {
+                    block_width,
+                    block_height,
+                },
+                index % 2 == 1,
+            )
+        }
+        format => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "KTX2 format {format:?}"
+            )))
+        }
+    };
+    Ok(data_format)
+}
+
+fn dxgi_format(format: DxgiFormat) -> Result<DataFormat, TextureError> {
+    let data_format = match format {
+        DxgiFormat::R8_UNorm => uncompressed(gl::R8, gl::RED, gl::UNSIGNED_BYTE),
+        DxgiFormat::R8G8_UNorm => uncompressed(gl::RG8, gl::RG, gl::UNSIGNED_BYTE),
+        DxgiFormat::R8G8B8A8_UNorm => uncompressed(gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        DxgiFormat::R8G8B8A8_UNorm_sRGB => {
+            uncompressed(gl::SRGB8_ALPHA8, gl::RGBA, gl::UNSIGNED_BYTE)
+        }
+        DxgiFormat::B8G8R8A8_UNorm => uncompressed(gl::RGBA8, gl::BGRA, gl::UNSIGNED_BYTE),
+        DxgiFormat::B8G8R8A8_UNorm_sRGB => {
+            uncompressed(gl::SRGB8_ALPHA8, gl::BGRA, gl::UNSIGNED_BYTE)
+        }
+        DxgiFormat::B8G8R8X8_UNorm => uncompressed(gl::RGB8, gl::BGRA, gl::UNSIGNED_BYTE),
+        DxgiFormat::R16_UNorm => uncompressed(gl::R16, gl::RED, gl::UNSIGNED_SHORT),
+        DxgiFormat::R16G16_UNorm => uncompressed(gl::RG16, gl::RG, gl::UNSIGNED_SHORT),
+        DxgiFormat::R16G16B16A16_UNorm => uncompressed(gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT),
+        DxgiFormat::R16_Float => uncompressed(gl::R16F, gl::RED, gl::HALF_FLOAT),
+        DxgiFormat::R16G16_Float => uncompressed(gl::RG16F, gl::RG, gl::HALF_FLOAT),
+        DxgiFormat::R16G16B16A16_Float => uncompressed(gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        DxgiFormat::R32_Float => uncompressed(gl::R32F, gl::RED, gl::FLOAT),
+        DxgiFormat::R32G32_Float => uncompressed(gl::RG32F, gl::RG, gl::FLOAT),
+        DxgiFormat::R32G32B32_Float => uncompressed(gl::RGB32F, gl::RGB, gl::FLOAT),
+        DxgiFormat::R32G32B32A32_Float => uncompressed(gl::RGBA32F, gl::RGBA, gl::FLOAT),
+        DxgiFormat::R11G11B10_Float => uncompressed(
+            gl::R11F_G11F_B10F,
+            gl::RGB,
+            gl::UNSIGNED_INT_10F_11F_11F_REV,
+        ),
+        DxgiFormat::R9G9B9E5_SharedExp => {
+            uncompressed(gl::RGB9_E5, gl::RGB, gl::UNSIGNED_INT_5_9_9_9_REV)
+        }
+        DxgiFormat::BC1_UNorm => compressed(CompressedFormat::Bc1 { alpha: true }, false),
+        DxgiFormat::BC1_UNorm_sRGB => compressed(CompressedFormat::Bc1 { alpha: true }, true),
+        DxgiFormat::BC2_UNorm => compressed(CompressedFormat::Bc2, false),
+        DxgiFormat::BC2_UNorm_sRGB => compressed(CompressedFormat::Bc2, true),
+        DxgiFormat::BC3_UNorm => compressed(CompressedFormat::Bc3, false),
+        DxgiFormat::BC3_UNorm_sRGB => compressed(CompressedFormat::Bc3, true),
+        DxgiFormat::BC4_UNorm => compressed(CompressedFormat::Bc4 { signed: false }, false),
+        DxgiFormat::BC4_SNorm => compressed(CompressedFormat::Bc4 { signed: true }, false),
+        DxgiFormat::BC5_UNorm => compressed(CompressedFormat::Bc5 { signed: false }, false),
+        DxgiFormat::BC5_SNorm => compressed(CompressedFormat::Bc5 { signed: true }, false),
+        DxgiFormat::BC6H_UF16 => compressed(CompressedFormat::Bc6h { signed: false }, false),
+        DxgiFormat::BC6H_SF16 => compressed(CompressedFormat::Bc6h { signed: true }, false),
+        DxgiFormat::BC7_UNorm => compressed(CompressedFormat::Bc7, false),
+        DxgiFormat::BC7_UNorm_sRGB => compressed(CompressedFormat::Bc7, true),
+        format => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "DDS format {format:?}"
+            )))
+        }
+    };
+    Ok(data_format)
+}
+
+fn d3d_format(format: D3DFormat) -> Result<DataFormat, TextureError> {
+    let data_format = match format {
+        D3DFormat::A8B8G8R8 => uncompressed(gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE),
+        D3DFormat::A8R8G8B8 => uncompressed(gl::RGBA8, gl::BGRA, gl::UNSIGNED_BYTE),
+        D3DFormat::X8R8G8B8 => uncompressed(gl::RGB8, gl::BGRA, gl::UNSIGNED_BYTE),
+        D3DFormat::A16B16G16R16F => uncompressed(gl::RGBA16F, gl::RGBA, gl::HALF_FLOAT),
+        D3DFormat::A32B32G32R32F => uncompressed(gl::RGBA32F, gl::RGBA, gl::FLOAT),
+        D3DFormat::DXT1 => compressed(CompressedFormat::Bc1 { alpha: true }, false),
+        D3DFormat::DXT2 | D3DFormat::DXT3 => compressed(CompressedFormat::Bc2, false),
+        D3DFormat::DXT4 | D3DFormat::DXT5 => compressed(CompressedFormat::Bc3, false),
+        format => {
+            return Err(TextureError::UnsupportedFormat(format!(
+                "DDS format {format:?}"
+            )))
+        }
+    };
+    Ok(data_format)
+}
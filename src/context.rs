@@ -0,0 +1,99 @@
+use gl::types::*;
+use std::ffi::CStr;
+
+/// The version of the current context, which is the one asked for through
+/// `glfw::WindowHint::ContextVersionMajor/Minor` or something newer.
+///
+/// Read from `GL_VERSION`, since `GL_MAJOR_VERSION` and `GL_MINOR_VERSION` only exist from
+/// 3.0 on. Returns (0, 0) when the string cannot be read.
+pub fn version() -> (u32, u32) {
+    let version = unsafe { gl::GetString(gl::VERSION) };
+    if version.is_null() {
+        return (0, 0);
+    }
+    let version = unsafe { CStr::from_ptr(version as *const _) };
+    parse_version(&version.to_string_lossy()).unwrap_or((0, 0))
+}
+
+// "4.6.0 NVIDIA 550.54", "2.1 Mesa 23.0.4" or "OpenGL ES 3.2 Mesa 23.0.4"
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let number = version
+        .split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = number.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts
+        .next()?
+        .trim_end_matches(|c: char| !c.is_ascii_digit())
+        .parse()
+        .ok()?;
+    Some((major, minor))
+}
+
+pub fn supports_version(major: u32, minor: u32) -> bool {
+    version() >= (major, minor)
+}
+
+/// Whether the driver advertises an extension, e.g. `GL_ARB_compute_shader`.
+pub fn has_extension(name: &str) -> bool {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count as GLuint).any(|index| {
+            let extension = gl::GetStringi(gl::EXTENSIONS, index);
+            !extension.is_null()
+                && CStr::from_ptr(extension as *const _).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+/// Turns `GL_FRAMEBUFFER_SRGB` on or off. While on, writes to an sRGB framebuffer encode the
+/// shader's linear output and blending happens on linear values. The default framebuffer
+/// is only sRGB when asked for with `glfw::WindowHint::SRgbCapable(true)`.
+pub fn set_framebuffer_srgb(enabled: bool) {
+    unsafe {
+        match enabled {
+            true => gl::Enable(gl::FRAMEBUFFER_SRGB),
+            false => gl::Disable(gl::FRAMEBUFFER_SRGB),
+        }
+    }
+}
+
+/// Whether the default framebuffer stores sRGB colour, which some drivers grant without the
+/// window hint and some refuse despite it.
+pub fn default_framebuffer_is_srgb() -> bool {
+    let mut encoding = 0;
+    unsafe {
+        let mut bound = 0;
+        gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut bound);
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+        gl::GetFramebufferAttachmentParameteriv(
+            gl::DRAW_FRAMEBUFFER,
+            gl::BACK_LEFT,
+            gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+            &mut encoding,
+        );
+        gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, bound as GLuint);
+    }
+    encoding as GLenum == gl::SRGB
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_strings() {
+        assert_eq!(parse_version("4.6.0 NVIDIA 550.54.14"), Some((4, 6)));
+        assert_eq!(parse_version("2.1 Mesa 23.0.4"), Some((2, 1)));
+        assert_eq!(
+            parse_version("3.3 (Core Profile) Mesa 23.0.4"),
+            Some((3, 3))
+        );
+        assert_eq!(parse_version("4.1 ATI-4.14.1"), Some((4, 1)));
+        assert_eq!(parse_version("OpenGL ES 3.2 Mesa 23.0.4"), Some((3, 2)));
+        assert_eq!(parse_version("2.0"), Some((2, 0)));
+        assert_eq!(parse_version("unknown"), None);
+        assert_eq!(parse_version("4"), None);
+    }
+}
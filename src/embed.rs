@@ -0,0 +1,24 @@
+/// Embeds a shader file as a `&'static CStr`, the path is relative to the calling file.
+///
+/// build.rs checks the file for GLSL syntax errors when it lives under `src/` and its name
+/// gives its stage: `.vert`, `.frag` or `.comp`, or `vertex.glsl` and the like.
+/// `#include`s are not resolved, use `ShaderSource` for shaders that need them.
+///
+/// ```ignore
+/// let shader = Shader::from_cstr(
+///     include_shader!("vertex.glsl"),
+///     include_shader!("fragment.glsl"),
+/// )?;
+/// ```
+#[macro_export]
+macro_rules! include_shader {
+    ($path:expr) => {{
+        const SOURCE: &::std::ffi::CStr = match ::std::ffi::CStr::from_bytes_with_nul(
+            concat!(include_str!($path), "\0").as_bytes(),
+        ) {
+            Ok(source) => source,
+            Err(_) => panic!(concat!($path, " contains a nul byte")),
+        };
+        SOURCE
+    }};
+}
@@ -0,0 +1,690 @@
+use crate::buffer::BufferTarget;
+use crate::shader::ShaderStage;
+use gl::types::{GLenum, GLuint};
+use std::{error::Error, ffi::NulError, fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io(io::Error),
+    Nul(NulError),
+    Compile {
+        stage: ShaderStage,
+        log: String,
+    },
+    Link {
+        log: String,
+    },
+    /// A file could not be included, or the includes form a cycle
+    Include {
+        path: PathBuf,
+        message: String,
+    },
+    /// The stage needs a newer context than the current one
+    Unsupported {
+        stage: ShaderStage,
+        required: (u32, u32),
+        available: (u32, u32),
+    },
+    /// The combination of stages cannot form a program
+    InvalidPipeline(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UniformError {
+    /// No active uniform has this name, it may have been optimized out
+    Unknown { name: String },
+    TypeMismatch {
+        name: String,
+        gl_type: GLenum,
+        value_type: &'static str,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeError {
+    /// The program reads an input at a location the layout does not provide
+    Missing { name: String, location: GLuint },
+    TypeMismatch {
+        name: String,
+        location: GLuint,
+        expected: GLenum,
+        found: GLenum,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockError {
+    /// No active uniform or storage block has this name, it may have been optimized out
+    Unknown { block: String },
+    /// The program's block and the Rust type differ in size, even with both padded to 16
+    /// bytes
+    SizeMismatch {
+        block: String,
+        program_size: usize,
+        rust_size: usize,
+    },
+    /// The program has a member the Rust type does not
+    MissingMember { block: String, member: String },
+    OffsetMismatch {
+        block: String,
+        member: String,
+        program_offset: usize,
+        rust_offset: usize,
+    },
+    /// An atomic counter buffer was given a different number of values than it has counters
+    CounterCount { expected: usize, found: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrawError {
+    /// The range runs past the indices, or the vertices of a mesh without indices
+    OutOfRange {
+        first: usize,
+        count: usize,
+        len: usize,
+    },
+    /// Indexed meshes take `DrawElementsIndirectCommand`, others `DrawArraysIndirectCommand`
+    CommandMismatch { indexed: bool },
+    /// Indirect commands are read from a `DrawIndirect` buffer only
+    WrongTarget(BufferTarget),
+}
+
+#[derive(Debug)]
+pub enum TextureError {
+    Image(image::ImageError),
+    /// The faces or layers of one texture are not all the same size
+    SizeMismatch {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    /// The faces or layers of one texture do not all have the same internal format
+    FormatMismatch {
+        expected: GLenum,
+        found: GLenum,
+    },
+    NotSquare {
+        width: u32,
+        height: u32,
+    },
+    /// A texture or the image it is generated from has no texels
+    ZeroSize {
+        width: u32,
+        height: u32,
+    },
+    /// The grid does not split the image into whole cells
+    InvalidGrid {
+        width: u32,
+        height: u32,
+        columns: u32,
+        rows: u32,
+    },
+    /// Raw texel data does not match the size of the texture
+    DataSize {
+        expected: usize,
+        found: usize,
+    },
+    /// A texture needs at least one layer
+    Empty,
+    /// Loaded texture data has more layers, faces or depth than the texture takes
+    Shape {
+        expected: &'static str,
+        layers: u32,
+        faces: u32,
+        depth: u32,
+    },
+    /// A KTX2 or DDS file that cannot be read
+    Container(String),
+    /// A texel format that cannot be uploaded
+    UnsupportedFormat(String),
+    /// A sprite does not fit on an atlas page with its padding and extrusion
+    SpriteTooLarge {
+        name: String,
+        width: u32,
+        height: u32,
+    },
+    /// A sprite with no texels cannot be packed into an atlas
+    EmptySprite(String),
+    /// The number of atlas page images does not match the lookup
+    PageCount {
+        expected: usize,
+        found: usize,
+    },
+}
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(io::Error),
+    Texture(TextureError),
+    Shader(ShaderError),
+    /// A custom loader failed, for formats this crate does not parse itself
+    Other(String),
+    /// The worker thread panicked while loading
+    Panicked,
+}
+
+/// A single message from a driver info log, pointing at a line of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Source string index, or the file number given by a `#line` directive
+    pub file: u32,
+    pub line: u32,
+    /// One based column, only reported by some drivers
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl ShaderError {
+    pub fn log(&self) -> Option<&str> {
+        match self {
+            ShaderError::Compile { log, .. } | ShaderError::Link { log } => Some(log),
+            _ => None,
+        }
+    }
+
+    /// Parses the info log into diagnostics, lines in an unknown format are skipped.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.log()
+            .map(|log| log.lines().filter_map(Diagnostic::parse).collect())
+            .unwrap_or_default()
+    }
+
+    /// Renders every diagnostic next to the line of `source` it refers to.
+    pub fn annotate(&self, source: &str) -> String {
+        let diagnostics = self.diagnostics();
+        if diagnostics.is_empty() {
+            return self.to_string();
+        }
+
+        let mut output = String::new();
+        for diagnostic in diagnostics {
+            output.push_str(&diagnostic.annotate(source));
+        }
+        output
+    }
+}
+
+impl Diagnostic {
+    // Drivers disagree on the format, the common ones are
+    //   Mesa:   0:12(5): error: ...
+    //   NVIDIA: 0(12) : error C0000: ...
+    //   AMD:    ERROR: 0:12: ...
+    pub fn parse(line: &str) -> Option<Diagnostic> {
+        let line = line.trim();
+        let (prefix, line) = match line.split_once(": ") {
+            Some((prefix, rest))
+                if prefix.eq_ignore_ascii_case("error")
+                    || prefix.eq_ignore_ascii_case("warning") =>
+            {
+                (Some(prefix), rest)
+            }
+            _ => (None, line),
+        };
+
+        let digits = line.find(|c: char| !c.is_ascii_digit())?;
+        let file = line[..digits].parse().ok()?;
+        let rest = &line[digits..];
+
+        let (line_number, column, rest) = if let Some(rest) = rest.strip_prefix(':') {
+            let end = rest.find(|c: char| !c.is_ascii_digit())?;
+            let line_number = rest[..end].parse().ok()?;
+            let rest = &rest[end..];
+            match rest.strip_prefix('(') {
+                Some(rest) => {
+                    let (column, rest) = rest.split_once(')')?;
+                    (line_number, column.parse().ok(), rest)
+                }
+                None => (line_number, None, rest),
+            }
+        } else {
+            let (line_number, rest) = rest.strip_prefix('(')?.split_once(')')?;
+            (line_number.parse().ok()?, None, rest)
+        };
+
+        let message = rest.trim_start().strip_prefix(':')?.trim();
+        let message = match prefix {
+            Some(prefix) => format!("{}: {message}", prefix.to_ascii_lowercase()),
+            None => message.to_string(),
+        };
+
+        Some(Diagnostic {
+            file,
+            line: line_number,
+            column,
+            message,
+        })
+    }
+
+    /// Renders the message followed by the offending line and a caret under it.
+    pub fn annotate(&self, source: &str) -> String {
+        self.render(&self.file.to_string(), source)
+    }
+
+    pub(crate) fn render(&self, file_name: &str, source: &str) -> String {
+        let mut output = format!("{file_name}:{}: {}\n", self.line, self.message);
+
+        let Some(text) = source.lines().nth((self.line as usize).saturating_sub(1)) else {
+            return output;
+        };
+        let gutter = format!("{:>4} | ", self.line);
+        let column = match self.column {
+            Some(column) => (column as usize).saturating_sub(1),
+            None => text.len() - text.trim_start().len(),
+        };
+
+        // Keep tabs so the caret lines up with the source line
+        let padding: String = text
+            .chars()
+            .take(column)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        output.push_str(&format!("{gutter}{text}\n"));
+        output.push_str(&format!("{:>4} | {padding}^\n", ""));
+        output
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderError::Io(error) => write!(f, "cannot read shader source: {error}"),
+            ShaderError::Nul(error) => write!(f, "shader source contains a nul byte: {error}"),
+            ShaderError::Compile { stage, log } => {
+                write!(f, "{stage} shader failed to compile:\n{log}")
+            }
+            ShaderError::Link { log } => write!(f, "shader program failed to link:\n{log}"),
+            ShaderError::Include { path, message } => {
+                write!(f, "cannot include {}: {message}", path.display())
+            }
+            ShaderError::Unsupported {
+                stage,
+                required,
+                available,
+            } => write!(
+                f,
+                "{stage} shaders need OpenGL {}.{}, the context is {}.{}",
+                required.0, required.1, available.0, available.1
+            ),
+            ShaderError::InvalidPipeline(message) => {
+                write!(f, "invalid shader pipeline: {message}")
+            }
+        }
+    }
+}
+
+impl Error for ShaderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ShaderError::Io(error) => Some(error),
+            ShaderError::Nul(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for UniformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UniformError::Unknown { name } => write!(f, "no active uniform named `{name}`"),
+            UniformError::TypeMismatch {
+                name,
+                gl_type,
+                value_type,
+            } => write!(
+                f,
+                "uniform `{name}` of GL type {gl_type:#06X} cannot be set from `{value_type}`"
+            ),
+        }
+    }
+}
+
+impl Error for UniformError {}
+
+impl fmt::Display for AttributeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeError::Missing { name, location } => {
+                write!(
+                    f,
+                    "no vertex attribute provided for `{name}` at location {location}"
+                )
+            }
+            AttributeError::TypeMismatch {
+                name,
+                location,
+                expected,
+                found,
+            } => write!(
+                f,
+                "vertex attribute `{name}` at location {location} has GL type {expected:#06X} \
+                 but the layout provides {found:#06X}"
+            ),
+        }
+    }
+}
+
+impl Error for AttributeError {}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::Unknown { block } => write!(f, "no active block named `{block}`"),
+            BlockError::SizeMismatch {
+                block,
+                program_size,
+                rust_size,
+            } => write!(
+                f,
+                "uniform block `{block}` takes {program_size} bytes but the Rust type has \
+                 {rust_size}, which differ even padded to 16 bytes"
+            ),
+            BlockError::MissingMember { block, member } => {
+                write!(
+                    f,
+                    "uniform block `{block}` has a member `{member}` the Rust type lacks"
+                )
+            }
+            BlockError::OffsetMismatch {
+                block,
+                member,
+                program_offset,
+                rust_offset,
+            } => write!(
+                f,
+                "member `{member}` of uniform block `{block}` is at offset {program_offset} \
+                 but at {rust_offset} in the Rust type"
+            ),
+            BlockError::CounterCount { expected, found } => write!(
+                f,
+                "atomic counter buffer has {expected} counters but {found} values were given"
+            ),
+        }
+    }
+}
+
+impl Error for BlockError {}
+
+impl fmt::Display for DrawError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawError::OutOfRange { first, count, len } => write!(
+                f,
+                "cannot draw {count} elements from {first}, the mesh has {len}"
+            ),
+            DrawError::CommandMismatch { indexed: true } => write!(
+                f,
+                "an indexed mesh needs DrawElementsIndirectCommand commands"
+            ),
+            DrawError::CommandMismatch { indexed: false } => write!(
+                f,
+                "a mesh without indices needs DrawArraysIndirectCommand commands"
+            ),
+            DrawError::WrongTarget(target) => write!(
+                f,
+                "indirect commands need a DrawIndirect buffer, not {target:?}"
+            ),
+        }
+    }
+}
+
+impl Error for DrawError {}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureError::Image(error) => write!(f, "cannot load texture image: {error}"),
+            TextureError::SizeMismatch { expected, found } => write!(
+                f,
+                "texture image is {}x{} but the others are {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            TextureError::FormatMismatch { expected, found } => write!(
+                f,
+                "texture image has internal format {found:#06X} but the others have \
+                 {expected:#06X}"
+            ),
+            TextureError::NotSquare { width, height } => {
+                write!(f, "cubemap faces must be square, not {width}x{height}")
+            }
+            TextureError::ZeroSize { width, height } => {
+                write!(f, "a {width}x{height} texture has no texels")
+            }
+            TextureError::InvalidGrid {
+                width,
+                height,
+                columns,
+                rows,
+            } => write!(
+                f,
+                "a {width}x{height} image cannot be split into {columns}x{rows} equal cells"
+            ),
+            TextureError::DataSize { expected, found } => write!(
+                f,
+                "texture needs {expected} bytes of texel data but {found} were given"
+            ),
+            TextureError::Empty => write!(f, "texture has no layers"),
+            TextureError::Shape {
+                expected,
+                layers,
+                faces,
+                depth,
+            } => write!(
+                f,
+                "texture data with {layers} layers, {faces} faces and a depth of {depth} is \
+                 not a {expected}"
+            ),
+            TextureError::Container(message) => write!(f, "{message}"),
+            TextureError::UnsupportedFormat(format) => {
+                write!(f, "unsupported texture format: {format}")
+            }
+            TextureError::SpriteTooLarge {
+                name,
+                width,
+                height,
+            } => write!(
+                f,
+                "sprite \"{name}\" of {width}x{height} does not fit on an atlas page"
+            ),
+            TextureError::EmptySprite(name) => write!(f, "sprite \"{name}\" has no texels"),
+            TextureError::PageCount { expected, found } => write!(
+                f,
+                "atlas lookup has {expected} pages but {found} page images were given"
+            ),
+        }
+    }
+}
+
+impl Error for TextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureError::Image(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(error: image::ImageError) -> Self {
+        TextureError::Image(error)
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(error) => write!(f, "cannot read asset: {error}"),
+            AssetError::Texture(error) => write!(f, "{error}"),
+            AssetError::Shader(error) => write!(f, "{error}"),
+            AssetError::Other(message) => write!(f, "{message}"),
+            AssetError::Panicked => write!(f, "asset loader panicked"),
+        }
+    }
+}
+
+impl Error for AssetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AssetError::Io(error) => Some(error),
+            AssetError::Texture(error) => Some(error),
+            AssetError::Shader(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AssetError {
+    fn from(error: io::Error) -> Self {
+        AssetError::Io(error)
+    }
+}
+
+impl From<TextureError> for AssetError {
+    fn from(error: TextureError) -> Self {
+        AssetError::Texture(error)
+    }
+}
+
+impl From<image::ImageError> for AssetError {
+    fn from(error: image::ImageError) -> Self {
+        AssetError::Texture(TextureError::Image(error))
+    }
+}
+
+impl From<ShaderError> for AssetError {
+    fn from(error: ShaderError) -> Self {
+        AssetError::Shader(error)
+    }
+}
+
+impl From<io::Error> for ShaderError {
+    fn from(error: io::Error) -> Self {
+        ShaderError::Io(error)
+    }
+}
+
+impl From<NulError> for ShaderError {
+    fn from(error: NulError) -> Self {
+        ShaderError::Nul(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(file: u32, line: u32, column: Option<u32>, message: &str) -> Diagnostic {
+        Diagnostic {
+            file,
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_mesa_logs() {
+        assert_eq!(
+            Diagnostic::parse("0:12(5): error: `colour' undeclared"),
+            Some(diagnostic(0, 12, Some(5), "error: `colour' undeclared"))
+        );
+        assert_eq!(
+            Diagnostic::parse("  1:3(10): warning: `x' used uninitialized  "),
+            Some(diagnostic(
+                1,
+                3,
+                Some(10),
+                "warning: `x' used uninitialized"
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_nvidia_logs() {
+        assert_eq!(
+            Diagnostic::parse("0(12) : error C0000: syntax error, unexpected '}'"),
+            Some(diagnostic(
+                0,
+                12,
+                None,
+                "error C0000: syntax error, unexpected '}'"
+            ))
+        );
+        assert_eq!(
+            Diagnostic::parse("2(7) : warning C7050: \"x\" might be used before being initialized"),
+            Some(diagnostic(
+                2,
+                7,
+                None,
+                "warning C7050: \"x\" might be used before being initialized"
+            ))
+        );
+    }
+
+    #[test]
+    fn parses_amd_logs() {
+        assert_eq!(
+            Diagnostic::parse("ERROR: 0:12: 'colour' : undeclared identifier"),
+            Some(diagnostic(
+                0,
+                12,
+                None,
+                "error: 'colour' : undeclared identifier"
+            ))
+        );
+        assert_eq!(
+            Diagnostic::parse("WARNING: 3:1: extension not supported"),
+            Some(diagnostic(3, 1, None, "warning: extension not supported"))
+        );
+    }
+
+    #[test]
+    fn skips_other_lines() {
+        assert_eq!(Diagnostic::parse(""), None);
+        assert_eq!(
+            Diagnostic::parse("ERROR: 1 compilation errors.  No code generated."),
+            None
+        );
+        assert_eq!(
+            Diagnostic::parse("Vertex shader failed to compile with the following errors:"),
+            None
+        );
+        assert_eq!(Diagnostic::parse("error: linking failed"), None);
+    }
+
+    #[test]
+    fn collects_diagnostics_from_a_log() {
+        let error = ShaderError::Compile {
+            stage: ShaderStage::Fragment,
+            log: "0:2(1): error: syntax error\nsome summary\n0:3(4): warning: unused\n".to_string(),
+        };
+        assert_eq!(
+            error.diagnostics(),
+            vec![
+                diagnostic(0, 2, Some(1), "error: syntax error"),
+                diagnostic(0, 3, Some(4), "warning: unused"),
+            ]
+        );
+        assert!(ShaderError::Io(io::ErrorKind::NotFound.into())
+            .diagnostics()
+            .is_empty());
+    }
+
+    #[test]
+    fn annotates_the_source_line() {
+        let source = "void main() {\n\tfloat x = y;\n}";
+        let annotated = diagnostic(0, 2, Some(12), "error: `y' undeclared").annotate(source);
+        assert_eq!(
+            annotated,
+            "0:2: error: `y' undeclared\n   2 | \tfloat x = y;\n     | \t          ^\n"
+        );
+
+        // Without a column the caret points at the start of the code
+        let annotated = diagnostic(0, 2, None, "error").annotate(source);
+        assert!(annotated.ends_with("     | \t^\n"));
+
+        // A line past the end only prints the message
+        assert_eq!(
+            diagnostic(0, 9, None, "error").annotate(source),
+            "0:9: error\n"
+        );
+    }
+}
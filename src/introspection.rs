@@ -0,0 +1,201 @@
+use gl::types::*;
+
+/// An active uniform of a linked program, as reported by `glGetActiveUniform`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveUniform {
+    pub name: String,
+    pub gl_type: GLenum,
+    /// Number of array elements, 1 for non arrays
+    pub size: GLint,
+    /// `None` for members of a uniform block
+    pub location: Option<GLint>,
+    /// Index of the uniform block this is a member of
+    pub block_index: Option<GLuint>,
+    /// Byte offset inside the uniform block
+    pub block_offset: Option<GLint>,
+}
+
+/// An active vertex input of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub gl_type: GLenum,
+    pub size: GLint,
+    /// `None` for built-in inputs such as `gl_VertexID`
+    pub location: Option<GLuint>,
+}
+
+/// An active uniform block of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveUniformBlock {
+    pub name: String,
+    pub index: GLuint,
+    pub binding: GLuint,
+    pub data_size: GLint,
+}
+
+pub(crate) unsafe fn active_uniforms(program: GLuint) -> Vec<ActiveUniform> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORMS, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_length);
+
+    let mut uniforms = Vec::with_capacity(count as usize);
+    let mut name = vec![0u8; max_length.max(1) as usize];
+    for index in 0..count as GLuint {
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        gl::GetActiveUniform(
+            program,
+            index,
+            name.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut gl_type,
+            name.as_mut_ptr() as *mut GLchar,
+        );
+
+        let mut block_index = 0;
+        gl::GetActiveUniformsiv(
+            program,
+            1,
+            &index,
+            gl::UNIFORM_BLOCK_INDEX,
+            &mut block_index,
+        );
+        let mut block_offset = 0;
+        gl::GetActiveUniformsiv(program, 1, &index, gl::UNIFORM_OFFSET, &mut block_offset);
+
+        let location = gl::GetUniformLocation(program, name.as_ptr() as *const GLchar);
+
+        uniforms.push(ActiveUniform {
+            name: String::from_utf8_lossy(&name[..length as usize]).into_owned(),
+            gl_type,
+            size,
+            location: (location >= 0).then_some(location),
+            block_index: (block_index >= 0).then_some(block_index as GLuint),
+            block_offset: (block_index >= 0).then_some(block_offset),
+        });
+    }
+
+    uniforms
+}
+
+pub(crate) unsafe fn active_attributes(program: GLuint) -> Vec<ActiveAttribute> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTES, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_length);
+
+    let mut attributes = Vec::with_capacity(count as usize);
+    let mut name = vec![0u8; max_length.max(1) as usize];
+    for index in 0..count as GLuint {
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        gl::GetActiveAttrib(
+            program,
+            index,
+            name.len() as GLsizei,
+            &mut length,
+            &mut size,
+            &mut gl_type,
+            name.as_mut_ptr() as *mut GLchar,
+        );
+
+        let location = gl::GetAttribLocation(program, name.as_ptr() as *const GLchar);
+
+        attributes.push(ActiveAttribute {
+            name: String::from_utf8_lossy(&name[..length as usize]).into_owned(),
+            gl_type,
+            size,
+            location: (location >= 0).then_some(location as GLuint),
+        });
+    }
+
+    attributes.sort_by_key(|attribute| attribute.location);
+    attributes
+}
+
+pub(crate) unsafe fn active_uniform_blocks(program: GLuint) -> Vec<ActiveUniformBlock> {
+    let mut count = 0;
+    gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+    let mut max_length = 0;
+    gl::GetProgramiv(
+        program,
+        gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH,
+        &mut max_length,
+    );
+
+    let mut blocks = Vec::with_capacity(count as usize);
+    let mut name = vec![0u8; max_length.max(1) as usize];
+    for index in 0..count as GLuint {
+        let mut length = 0;
+        gl::GetActiveUniformBlockName(
+            program,
+            index,
+            name.len() as GLsizei,
+            &mut length,
+            name.as_mut_ptr() as *mut GLchar,
+        );
+
+        let mut binding = 0;
+        gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_BINDING, &mut binding);
+        let mut data_size = 0;
+        gl::GetActiveUniformBlockiv(program, index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut data_size);
+
+        blocks.push(ActiveUniformBlock {
+            name: String::from_utf8_lossy(&name[..length as usize]).into_owned(),
+            index,
+            binding: binding as GLuint,
+            data_size,
+        });
+    }
+
+    blocks
+}
+
+/// The GLSL spelling of a type reported by introspection, e.g. `vec3` for `GL_FLOAT_VEC3`.
+pub fn glsl_type_name(gl_type: GLenum) -> Option<&'static str> {
+    let name = match gl_type {
+        gl::FLOAT => "float",
+        gl::FLOAT_VEC2 => "vec2",
+        gl::FLOAT_VEC3 => "vec3",
+        gl::FLOAT_VEC4 => "vec4",
+        gl::DOUBLE => "double",
+        gl::INT => "int",
+        gl::INT_VEC2 => "ivec2",
+        gl::INT_VEC3 => "ivec3",
+        gl::INT_VEC4 => "ivec4",
+        gl::UNSIGNED_INT => "uint",
+        gl::UNSIGNED_INT_VEC2 => "uvec2",
+        gl::UNSIGNED_INT_VEC3 => "uvec3",
+        gl::UNSIGNED_INT_VEC4 => "uvec4",
+        gl::BOOL => "bool",
+        gl::BOOL_VEC2 => "bvec2",
+        gl::BOOL_VEC3 => "bvec3",
+        gl::BOOL_VEC4 => "bvec4",
+        gl::FLOAT_MAT2 => "mat2",
+        gl::FLOAT_MAT3 => "mat3",
+        gl::FLOAT_MAT4 => "mat4",
+        gl::FLOAT_MAT2x3 => "mat2x3",
+        gl::FLOAT_MAT2x4 => "mat2x4",
+        gl::FLOAT_MAT3x2 => "mat3x2",
+        gl::FLOAT_MAT3x4 => "mat3x4",
+        gl::FLOAT_MAT4x2 => "mat4x2",
+        gl::FLOAT_MAT4x3 => "mat4x3",
+        gl::SAMPLER_1D => "sampler1D",
+        gl::SAMPLER_2D => "sampler2D",
+        gl::SAMPLER_3D => "sampler3D",
+        gl::SAMPLER_CUBE => "samplerCube",
+        gl::SAMPLER_2D_SHADOW => "sampler2DShadow",
+        gl::SAMPLER_2D_ARRAY => "sampler2DArray",
+        gl::SAMPLER_2D_ARRAY_SHADOW => "sampler2DArrayShadow",
+        gl::SAMPLER_CUBE_SHADOW => "samplerCubeShadow",
+        gl::INT_SAMPLER_2D => "isampler2D",
+        gl::UNSIGNED_INT_SAMPLER_2D => "usampler2D",
+        _ => return None,
+    };
+    Some(name)
+}
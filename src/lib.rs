@@ -0,0 +1,56 @@
+pub mod atlas;
+pub mod buffer;
+pub mod compressed;
+pub mod compute;
+pub mod container;
+pub mod context;
+mod embed;
+pub mod error;
+pub mod introspection;
+pub mod loader;
+pub mod mesh;
+pub mod mipmap;
+pub mod preprocess;
+pub mod program_cache;
+pub mod reload;
+pub mod sampler;
+pub mod shader;
+pub mod std140;
+pub mod std430;
+pub mod stream;
+pub mod texture;
+pub mod uniform;
+pub mod vertex;
+
+pub use atlas::{Atlas, AtlasBuilder, AtlasLookup, AtlasRegion};
+pub use buffer::{Buffer, BufferTarget, BufferUsage};
+pub use bytemuck::{Pod, Zeroable};
+pub use compressed::CompressedFormat;
+pub use compute::{memory_barrier, Barrier, ComputeProgram};
+pub use container::{DataFormat, TextureData};
+pub use error::{
+    AssetError, AttributeError, BlockError, Diagnostic, DrawError, ShaderError, TextureError,
+    UniformError,
+};
+pub use introspection::{ActiveAttribute, ActiveUniform, ActiveUniformBlock};
+pub use loader::{Asset, AssetLoader};
+pub use mesh::{
+    DrawArraysIndirectCommand, DrawElementsIndirectCommand, IndirectCommand, Mesh, Primitive,
+};
+pub use mipmap::{MipmapFilter, MipmapOptions};
+pub use opengl_derive::{Std140, Std430, Vertex};
+pub use preprocess::{Defines, ShaderSource};
+pub use program_cache::ProgramCache;
+pub use reload::ReloadableShader;
+pub use sampler::{CompareFunction, Filter, Sampler, SamplerCache, SamplerDesc, Wrap};
+pub use shader::{Shader, ShaderBuilder, ShaderStage, ShaderVariants};
+pub use std140::{Std140, UniformBuffer};
+pub use std430::{AtomicCounterBuffer, Std430, StorageBuffer};
+pub use stream::StreamBuffer;
+pub use texture::{
+    ColorSpace, PixelFormat, Texture2D, Texture2DArray, Texture3D, TextureCube, TextureOptions,
+};
+pub use uniform::{TextureUnit, Uniform};
+pub use vertex::{
+    AttributeFormat, AttributeKind, Index, Vertex, VertexArray, VertexArrayBuilder, VertexAttribute,
+};
@@ -0,0 +1,368 @@
+use crate::container::TextureData;
+use crate::error::AssetError;
+use crate::mesh::{Mesh, Primitive};
+use crate::preprocess::{Defines, ShaderSource};
+use crate::shader::Shader;
+use crate::texture::{PreparedImage, Texture2D, TextureOptions};
+use crate::vertex::{Index, Vertex};
+use std::any::Any;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// CPU data a worker finished, type erased until its upload downcasts it
+type Loaded = Result<Box<dyn Any + Send>, AssetError>;
+type Job = Box<dyn FnOnce() -> Loaded + Send>;
+type Upload = Box<dyn FnOnce(Loaded)>;
+
+/// A handle to an asset that is still loading, shared with the loader.
+///
+/// It stays empty until `AssetLoader::update` uploads the asset, so draw with a placeholder
+/// in the meantime. Dropping every clone of a handle skips the upload.
+pub struct Asset<T> {
+    slot: Rc<OnceCell<Result<T, AssetError>>>,
+}
+
+impl<T> Asset<T> {
+    /// The asset once it is uploaded, `None` while loading or after it failed.
+    pub fn get(&self) -> Option<&T> {
+        self.slot.get().and_then(|result| result.as_ref().ok())
+    }
+
+    /// The asset once it is uploaded, `placeholder` until then or if it failed.
+    pub fn get_or<'a>(&'a self, placeholder: &'a T) -> &'a T {
+        self.get().unwrap_or(placeholder)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.get().is_some()
+    }
+
+    /// True once the asset is uploaded or has failed.
+    pub fn is_done(&self) -> bool {
+        self.slot.get().is_some()
+    }
+
+    pub fn error(&self) -> Option<&AssetError> {
+        self.slot.get().and_then(|result| result.as_ref().err())
+    }
+}
+
+impl<T> Clone for Asset<T> {
+    fn clone(&self) -> Self {
+        Asset {
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+/// Loads assets on worker threads and uploads them on the GL thread.
+///
+/// Workers decode images, read shader files and parse models, then queue the CPU data. The
+/// queue is bounded, so workers wait instead of piling up decoded data when the GL thread
+/// falls behind. Call `update` once per frame to upload what is ready within a time budget.
+///
+/// The loader and its handles belong to the GL thread. Workers exit once the loader is
+/// dropped and the job they are on finishes.
+pub struct AssetLoader {
+    jobs: mpsc::Sender<(u64, Job)>,
+    finished: mpsc::Receiver<(u64, Loaded)>,
+    uploads: HashMap<u64, Upload>,
+    next_id: u64,
+}
+
+impl AssetLoader {
+    /// Starts `workers` threads, which wait once `queue_capacity` loaded assets are waiting
+    /// for an upload.
+    pub fn new(workers: usize, queue_capacity: usize) -> AssetLoader {
+        let (jobs, job_receiver) = mpsc::channel::<(u64, Job)>();
+        let (finished_sender, finished) = mpsc::sync_channel(queue_capacity);
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        for index in 0..workers.max(1) {
+            let job_receiver = job_receiver.clone();
+            let finished_sender = finished_sender.clone();
+            thread::Builder::new()
+                .name(format!("asset loader {index}"))
+                .spawn(move || loop {
+                    // The lock is released before the job runs, so the others can take jobs
+                    let next = job_receiver.lock().unwrap().recv();
+                    let Ok((id, job)) = next else {
+                        break;
+                    };
+                    let loaded = panic::catch_unwind(AssertUnwindSafe(job))
+                        .unwrap_or(Err(AssetError::Panicked));
+                    if finished_sender.send((id, loaded)).is_err() {
+                        break;
+                    }
+                })
+                .expect("Cannot spawn asset loader thread");
+        }
+
+        AssetLoader {
+            jobs,
+            finished,
+            uploads: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Runs `load` on a worker and `upload` on the GL thread with its result, for assets
+    /// this crate has no loader for, such as models.
+    pub fn load<T, U>(
+        &mut self,
+        load: impl FnOnce() -> Result<T, AssetError> + Send + 'static,
+        upload: impl FnOnce(T) -> Result<U, AssetError> + 'static,
+    ) -> Asset<U>
+    where
+        T: Send + 'static,
+        U: 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let asset = Asset {
+            slot: Rc::new(OnceCell::new()),
+        };
+        let slot: Weak<OnceCell<Result<U, AssetError>>> = Rc::downgrade(&asset.slot);
+        self.uploads.insert(
+            id,
+            Box::new(move |loaded: Loaded| {
+                // Nobody is waiting for it any more
+                let Some(slot) = slot.upgrade() else {
+                    return;
+                };
+                let result = loaded.and_then(|data| {
+                    let data = *data.downcast::<T>().expect("asset loaded as another type");
+                    upload(data)
+                });
+                let _ = slot.set(result);
+            }),
+        );
+
+        let job: Job = Box::new(move || load().map(|data| Box::new(data) as Box<dyn Any + Send>));
+        // Workers only stop once the loader is dropped, so this cannot fail
+        self.jobs.send((id, job)).unwrap();
+        asset
+    }
+
+    /// Decodes an image file, flips it and builds CPU mipmaps on a worker.
+    pub fn texture(&mut self, path: &Path, options: TextureOptions) -> Asset<Texture2D> {
+        let path = path.to_path_buf();
+        self.load(
+            move || Ok(PreparedImage::new(image::open(path)?, options)),
+            |prepared| Ok(Texture2D::from_prepared(&prepared)),
+        )
+    }
+
+    /// Reads a KTX2 or DDS file on a worker.
+    pub fn texture_data(&mut self, path: &Path) -> Asset<Texture2D> {
+        let path = path.to_path_buf();
+        self.load(
+            move || Ok(TextureData::from_file(&path)?),
+            |data| Ok(Texture2D::from_data(&data)?),
+        )
+    }
+
+    /// Reads both sources and resolves their `#include`s on a worker, compiling is left
+    /// to the GL thread.
+    pub fn shader(
+        &mut self,
+        vertex_source_path: &Path,
+        fragment_source_path: &Path,
+        defines: &Defines,
+    ) -> Asset<Shader> {
+        let paths: (PathBuf, PathBuf) = (
+            vertex_source_path.to_path_buf(),
+            fragment_source_path.to_path_buf(),
+        );
+        let defines = defines.clone();
+        self.load(
+            move || {
+                let vertex_source = ShaderSource::from_file(&paths.0, &defines)?;
+                let fragment_source = ShaderSource::from_file(&paths.1, &defines)?;
+                Ok((vertex_source, fragment_source))
+            },
+            |(vertex_source, fragment_source)| {
+                Ok(Shader::from_source(&vertex_source, &fragment_source)?)
+            },
+        )
+    }
+
+    /// Runs a model parser on a worker and uploads the indexed mesh it returns.
+    pub fn mesh<V, I>(
+        &mut self,
+        primitive: Primitive,
+        parse: impl FnOnce() -> Result<(Vec<V>, Vec<I>), AssetError> + Send + 'static,
+    ) -> Asset<Mesh<V, I>>
+    where
+        V: Vertex + Send,
+        I: Index + Send,
+    {
+        self.load(parse, move |(vertices, indices)| {
+            Ok(Mesh::indexed(primitive, &vertices, &indices))
+        })
+    }
+
+    /// Uploads finished assets until `budget` is spent, call this once per frame.
+    ///
+    /// At least one asset is uploaded when any is ready, so a budget smaller than a single
+    /// upload still makes progress. Returns how many were uploaded.
+    pub fn update(&mut self, budget: Duration) -> usize {
+        let start = Instant::now();
+        let mut uploaded = 0;
+        while uploaded == 0 || start.elapsed() < budget {
+            let Ok((id, loaded)) = self.finished.try_recv() else {
+                break;
+            };
+            if let Some(upload) = self.uploads.remove(&id) {
+                upload(loaded);
+            }
+            uploaded += 1;
+        }
+        uploaded
+    }
+
+    /// Uploads everything, waiting for the workers to finish what was requested.
+    pub fn finish(&mut self) {
+        while !self.uploads.is_empty() {
+            let Ok((id, loaded)) = self.finished.recv() else {
+                break;
+            };
+            if let Some(upload) = self.uploads.remove(&id) {
+                upload(loaded);
+            }
+        }
+    }
+
+    /// Assets requested but not uploaded yet.
+    pub fn pending(&self) -> usize {
+        self.uploads.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Upload with `update(Duration::ZERO)` until nothing is pending, checking each call
+    // makes progress on no more than one asset
+    fn drain(loader: &mut AssetLoader) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while loader.pending() > 0 {
+            assert!(Instant::now() < deadline, "assets never finished loading");
+            assert!(loader.update(Duration::ZERO) <= 1);
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn handles_wait_for_update() {
+        let mut loader = AssetLoader::new(2, 4);
+        let (loaded, wait) = mpsc::channel();
+        let asset = loader.load(
+            move || {
+                loaded.send(()).unwrap();
+                Ok(21)
+            },
+            |value: i32| Ok(value * 2),
+        );
+
+        // Loaded on the worker but not uploaded yet
+        wait.recv().unwrap();
+        assert!(!asset.is_ready() && !asset.is_done());
+        assert_eq!(asset.get(), None);
+        assert_eq!(*asset.get_or(&-1), -1);
+
+        drain(&mut loader);
+        assert!(asset.is_ready() && asset.is_done());
+        assert_eq!(asset.get(), Some(&42));
+        assert_eq!(*asset.clone().get_or(&-1), 42);
+        assert!(asset.error().is_none());
+    }
+
+    #[test]
+    fn zero_budgets_upload_one_asset_per_update() {
+        let mut loader = AssetLoader::new(2, 8);
+        let assets: Vec<Asset<usize>> = (0..5)
+            .map(|index| loader.load(move || Ok(index), Ok))
+            .collect();
+        assert_eq!(loader.pending(), 5);
+
+        drain(&mut loader);
+        for (index, asset) in assets.iter().enumerate() {
+            assert_eq!(asset.get(), Some(&index));
+        }
+    }
+
+    #[test]
+    fn errors_reach_the_handle() {
+        let mut loader = AssetLoader::new(1, 4);
+        let failed = loader.load(
+            || Err::<(), _>(AssetError::Other("cannot parse".to_string())),
+            |_| Ok(()),
+        );
+        let rejected = loader.load(|| Ok(()), |_| Err::<(), _>(AssetError::Panicked));
+        let panicked = loader.load(|| -> Result<(), AssetError> { panic!("worker") }, Ok);
+        loader.finish();
+
+        for asset in [&failed, &rejected, &panicked] {
+            assert!(asset.is_done() && !asset.is_ready());
+            assert_eq!(*asset.get_or(&()), ());
+        }
+        assert!(
+            matches!(failed.error(), Some(AssetError::Other(message)) if message == "cannot parse")
+        );
+        assert!(matches!(rejected.error(), Some(AssetError::Panicked)));
+        assert!(matches!(panicked.error(), Some(AssetError::Panicked)));
+    }
+
+    #[test]
+    fn dropped_handles_skip_the_upload() {
+        let mut loader = AssetLoader::new(1, 4);
+        let uploaded = Rc::new(OnceCell::new());
+        let flag = uploaded.clone();
+        drop(loader.load(
+            || Ok(()),
+            move |_| {
+                flag.set(()).unwrap();
+                Ok(())
+            },
+        ));
+        loader.finish();
+        assert_eq!(loader.pending(), 0);
+        assert!(uploaded.get().is_none());
+    }
+
+    #[test]
+    fn workers_wait_for_a_full_queue() {
+        let mut loader = AssetLoader::new(1, 1);
+        let started = Arc::new(AtomicUsize::new(0));
+        let assets: Vec<Asset<()>> = (0..4)
+            .map(|_| {
+                let started = started.clone();
+                loader.load(
+                    move || {
+                        started.fetch_add(1, Ordering::SeqCst);
+                        Ok(())
+                    },
+                    Ok,
+                )
+            })
+            .collect();
+
+        // One asset waits in the queue and the worker holds the next, the rest wait
+        thread::sleep(Duration::from_millis(50));
+        assert!(started.load(Ordering::SeqCst) <= 2);
+
+        loader.finish();
+        assert_eq!(started.load(Ordering::SeqCst), 4);
+        assert!(assets.iter().all(Asset::is_ready));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::buffer::{Buffer, BufferTarget, BufferUsage};
+use crate::error::DrawError;
+use crate::vertex::{Index, Vertex, VertexArray};
+use bytemuck::{Pod, Zeroable};
+use gl::types::*;
+
+/// How vertices are assembled into primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Primitive {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            Primitive::Points => gl::POINTS,
+            Primitive::Lines => gl::LINES,
+            Primitive::LineStrip => gl::LINE_STRIP,
+            Primitive::LineLoop => gl::LINE_LOOP,
+            Primitive::Triangles => gl::TRIANGLES,
+            Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+            Primitive::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+}
+
+/// One draw of an indexed mesh read by `Mesh::multi_draw_indirect`, laid out as OpenGL
+/// expects.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawElementsIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+
+/// One draw of a mesh without indices read by `Mesh::multi_draw_indirect`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Pod, Zeroable)]
+pub struct DrawArraysIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first: u32,
+    pub base_instance: u32,
+}
+
+/// A draw command stored in a `BufferTarget::DrawIndirect` buffer.
+pub trait IndirectCommand: Pod {
+    /// Whether the commands index into the index buffer.
+    const INDEXED: bool;
+}
+
+impl IndirectCommand for DrawElementsIndirectCommand {
+    const INDEXED: bool = true;
+}
+
+impl IndirectCommand for DrawArraysIndirectCommand {
+    const INDEXED: bool = false;
+}
+
+/// Vertices, optional indices of type `I` and the vertex array reading them.
+///
+/// Per-instance buffers added with `instance_buffer` are not owned, keep them alive for as
+/// long as the mesh is drawn.
+pub struct Mesh<V: Vertex, I: Index = u32> {
+    vertices: Buffer<V>,
+    indices: Option<Buffer<I>>,
+    vertex_array: VertexArray,
+    primitive: Primitive,
+}
+
+impl<V: Vertex> Mesh<V> {
+    /// A mesh drawn straight from its vertices.
+    pub fn new(primitive: Primitive, vertices: &[V]) -> Mesh<V> {
+        let vertices = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, vertices);
+        let vertex_array = VertexArray::builder().vertex_buffer(&vertices).build();
+
+        Mesh {
+            vertices,
+            indices: None,
+            vertex_array,
+            primitive,
+        }
+    }
+}
+
+impl<V: Vertex, I: Index> Mesh<V, I> {
+    /// A mesh drawn through `indices`, which may be `u8`, `u16` or `u32`.
+    pub fn indexed(primitive: Primitive, vertices: &[V], indices: &[I]) -> Mesh<V, I> {
+        let vertices = Buffer::from_slice(BufferTarget::Array, BufferUsage::StaticDraw, vertices);
+        let indices =
+            Buffer::from_slice(BufferTarget::ElementArray, BufferUsage::StaticDraw, indices);
+        let vertex_array = VertexArray::builder()
+            .vertex_buffer(&vertices)
+            .index_buffer(&indices)
+            .build();
+
+        Mesh {
+            vertices,
+            indices: Some(indices),
+            vertex_array,
+            primitive,
+        }
+    }
+
+    /// Reads the attributes of `T` from `buffer`, advancing one element every `divisor`
+    /// instances.
+    pub fn instance_buffer<T: Vertex>(self, buffer: &Buffer<T>, divisor: GLuint) -> Self {
+        self.vertex_array.add_buffer(buffer, divisor);
+        self
+    }
+
+    pub fn primitive(&self) -> Primitive {
+        self.primitive
+    }
+
+    pub fn vertices(&self) -> &Buffer<V> {
+        &self.vertices
+    }
+
+    /// The vertex buffer, growing it keeps the vertex array valid.
+    pub fn vertices_mut(&mut self) -> &mut Buffer<V> {
+        &mut self.vertices
+    }
+
+    pub fn indices(&self) -> Option<&Buffer<I>> {
+        self.indices.as_ref()
+    }
+
+    pub fn indices_mut(&mut self) -> Option<&mut Buffer<I>> {
+        self.indices.as_mut()
+    }
+
+    pub fn vertex_array(&self) -> &VertexArray {
+        &self.vertex_array
+    }
+
+    // Indices if there are any, vertices otherwise
+    fn count(&self) -> usize {
+        match &self.indices {
+            Some(indices) => indices.len(),
+            None => self.vertices.len(),
+        }
+    }
+
+    /// Draws the whole mesh with the program in use.
+    pub fn draw(&self) {
+        self.draw_instanced(1);
+    }
+
+    /// Draws the whole mesh `instances` times, per-instance attributes advance by their
+    /// divisor and `gl_InstanceID` counts up.
+    pub fn draw_instanced(&self, instances: usize) {
+        self.vertex_array.bind();
+        let mode = self.primitive.gl_enum();
+        let count = self.count() as GLsizei;
+        unsafe {
+            match self.indices {
+                Some(_) => gl::DrawElementsInstanced(
+                    mode,
+                    count,
+                    I::GL_TYPE,
+                    std::ptr::null(),
+                    instances as GLsizei,
+                ),
+                None => gl::DrawArraysInstanced(mode, 0, count, instances as GLsizei),
+            }
+            gl::BindVertexArray(0);
+        }
+    }
+
+    /// Draws `count` elements starting at `first`, counted in indices for indexed meshes and
+    /// vertices otherwise. `base_vertex` is added to every index, which lets several meshes
+    /// share one buffer with indices relative to their own first vertex.
+    pub fn draw_range(
+        &self,
+        first: usize,
+        count: usize,
+        base_vertex: GLint,
+    ) -> Result<(), DrawError> {
+        let len = self.count();
+        if first.checked_add(count).is_none_or(|end| end > len) {
+            return Err(DrawError::OutOfRange { first, count, len });
+        }
+
+        self.vertex_array.bind();
+        let mode = self.primitive.gl_enum();
+        unsafe {
+            match self.indices {
+                Some(_) => gl::DrawElementsBaseVertex(
+                    mode,
+                    count as GLsizei,
+                    I::GL_TYPE,
+                    (first * size_of::<I>()) as *const _,
+                    base_vertex,
+                ),
+                None => gl::DrawArrays(mode, first as GLint + base_vertex, count as GLsizei),
+            }
+            gl::BindVertexArray(0);
+        }
+        Ok(())
+    }
+
+    /// Issues every command in `commands` in one call, needs OpenGL 4.3.
+    ///
+    /// Indexed meshes take `DrawElementsIndirectCommand`, others `DrawArraysIndirectCommand`.
+    /// The commands can be written by a compute shader, as long as
+    /// `memory_barrier(Barrier::COMMAND)` comes in between.
+    pub fn multi_draw_indirect<C: IndirectCommand>(
+        &self,
+        commands: &Buffer<C>,
+    ) -> Result<(), DrawError> {
+        let indexed = self.indices.is_some();
+        if C::INDEXED != indexed {
+            return Err(DrawError::CommandMismatch { indexed });
+        }
+        if commands.target() != BufferTarget::DrawIndirect {
+            return Err(DrawError::WrongTarget(commands.target()));
+        }
+
+        self.vertex_array.bind();
+        commands.bind();
+        let mode = self.primitive.gl_enum();
+        let count = commands.len() as GLsizei;
+        unsafe {
+            match C::INDEXED {
+                true => gl::MultiDrawElementsIndirect(mode, I::GL_TYPE, std::ptr::null(), count, 0),
+                false => gl::MultiDrawArraysIndirect(mode, std::ptr::null(), count, 0),
+            }
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0);
+        }
+        Ok(())
+    }
+}
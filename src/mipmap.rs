@@ -0,0 +1,373 @@
+use image::{DynamicImage, Rgba32FImage};
+use std::f32::consts::PI;
+
+/// The filter `mip_chain` downsamples with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MipmapFilter {
+    /// Averages the texels each level covers, fast but soft
+    Box,
+    /// Three lobe Lanczos, sharp with some ringing around hard edges
+    Lanczos,
+    /// Kaiser windowed sinc, nearly as sharp as Lanczos with less ringing
+    Kaiser,
+}
+
+// Shape of the Kaiser window, higher trades sharpness for less ringing
+const KAISER_ALPHA: f32 = 4.0;
+
+impl MipmapFilter {
+    // How far the filter reaches, in texels of the smaller level
+    fn radius(self) -> f32 {
+        match self {
+            MipmapFilter::Box => 0.5,
+            MipmapFilter::Lanczos | MipmapFilter::Kaiser => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let radius = self.radius();
+        match self {
+            MipmapFilter::Box if x.abs() <= radius => 1.0,
+            MipmapFilter::Lanczos if x.abs() < radius => sinc(x) * sinc(x / radius),
+            MipmapFilter::Kaiser if x.abs() < radius => {
+                let window = (1.0 - (x / radius).powi(2)).sqrt();
+                sinc(x) * bessel_i0(KAISER_ALPHA * window) / bessel_i0(KAISER_ALPHA)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+// Modified Bessel function of the first kind, summed until the terms stop mattering
+fn bessel_i0(x: f32) -> f32 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-7 {
+        term *= (x / (2.0 * k)).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+/// How `mip_chain` builds each level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MipmapOptions {
+    pub filter: MipmapFilter,
+    /// Keeps the share of texels whose alpha passes this reference the same on every level,
+    /// so alpha tested art does not thin out and vanish in the distance
+    pub alpha_coverage: Option<f32>,
+}
+
+impl Default for MipmapOptions {
+    fn default() -> MipmapOptions {
+        MipmapOptions {
+            filter: MipmapFilter::Kaiser,
+            alpha_coverage: None,
+        }
+    }
+}
+
+/// Every mip level below `image` down to 1x1, each the same type as `image`.
+///
+/// With `srgb` the colour channels are filtered in linear light, so dark and bright
+/// details average the way they look rather than darkening the smaller levels.
+pub fn mip_chain(image: &DynamicImage, options: &MipmapOptions, srgb: bool) -> Vec<DynamicImage> {
+    let mut current = image.to_rgba32f();
+    if srgb {
+        map_color(&mut current, srgb_to_linear);
+    }
+    let coverage = options
+        .alpha_coverage
+        .map(|reference| (reference, alpha_coverage(&current, reference)));
+
+    let mut levels = Vec::new();
+    while current.width() > 1 || current.height() > 1 {
+        let (width, height) = ((current.width() / 2).max(1), (current.height() / 2).max(1));
+        current = resample(&current, width, height, options.filter);
+
+        // Adjusted copies only, the next level is filtered from the unadjusted one
+        let mut level = current.clone();
+        if let Some((reference, coverage)) = coverage {
+            scale_alpha_to_coverage(&mut level, reference, coverage);
+        }
+        if srgb {
+            map_color(&mut level, linear_to_srgb);
+        }
+        levels.push(convert_like(level, image));
+    }
+    levels
+}
+
+// For each texel of the smaller side, the texels of the larger side it reads and their
+// weights, which add up to one
+fn weights(source: u32, destination: u32, filter: MipmapFilter) -> Vec<Vec<(u32, f32)>> {
+    let scale = source as f32 / destination as f32;
+    let support = filter.radius() * scale;
+    (0..destination)
+        .map(|index| {
+            let center = (index as f32 + 0.5) * scale;
+            let first = (center - support).floor() as i64;
+            let last = (center + support).ceil() as i64;
+            let mut taps: Vec<(u32, f32)> = (first..=last)
+                .map(|texel| {
+                    let weight = filter.weight((texel as f32 + 0.5 - center) / scale);
+                    // Edges are clamped, repeating would bleed the opposite side in
+                    (texel.clamp(0, source as i64 - 1) as u32, weight)
+                })
+                .filter(|&(_, weight)| weight != 0.0)
+                .collect();
+            let total: f32 = taps.iter().map(|&(_, weight)| weight).sum();
+            for (_, weight) in &mut taps {
+                *weight /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+// Separable resampling, across the rows first and then down the columns
+fn resample(source: &Rgba32FImage, width: u32, height: u32, filter: MipmapFilter) -> Rgba32FImage {
+    let horizontal = weights(source.width(), width, filter);
+    let vertical = weights(source.height(), height, filter);
+    let apply = |taps: &[(u32, f32)], texel: &dyn Fn(u32) -> [f32; 4]| {
+        let mut sum = [0.0; 4];
+        for &(index, weight) in taps {
+            let value = texel(index);
+            for channel in 0..4 {
+                sum[channel] += value[channel] * weight;
+            }
+        }
+        image::Rgba(sum)
+    };
+
+    let rows = Rgba32FImage::from_fn(width, source.height(), |x, y| {
+        apply(&horizontal[x as usize], &|index| {
+            source.get_pixel(index, y).0
+        })
+    });
+    Rgba32FImage::from_fn(width, height, |x, y| {
+        apply(&vertical[y as usize], &|index| rows.get_pixel(x, index).0)
+    })
+}
+
+// Share of texels whose alpha passes `reference`
+fn alpha_coverage(image: &Rgba32FImage, reference: f32) -> f32 {
+    let passing = image.pixels().filter(|pixel| pixel[3] > reference).count();
+    passing as f32 / (image.width() * image.height()) as f32
+}
+
+// Scale alpha so `coverage` of the texels pass `reference`, by finding the threshold that
+// many texels already pass and mapping it onto the reference
+fn scale_alpha_to_coverage(image: &mut Rgba32FImage, reference: f32, coverage: f32) {
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..16 {
+        let threshold = (low + high) / 2.0;
+        if alpha_coverage(image, threshold) > coverage {
+            low = threshold;
+        } else {
+            high = threshold;
+        }
+    }
+
+    let threshold: f32 = (low + high) / 2.0;
+    let scale = reference / threshold.max(1e-4);
+    for pixel in image.pixels_mut() {
+        pixel[3] = (pixel[3] * scale).min(1.0);
+    }
+}
+
+fn map_color(image: &mut Rgba32FImage, function: fn(f32) -> f32) {
+    for pixel in image.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = function(pixel[channel]);
+        }
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    let value = value.max(0.0);
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Convert a level back to the pixel type of the image it was built from
+fn convert_like(level: Rgba32FImage, like: &DynamicImage) -> DynamicImage {
+    let level = DynamicImage::ImageRgba32F(level);
+    match like {
+        DynamicImage::ImageLuma8(_) => level.to_luma8().into(),
+        DynamicImage::ImageLumaA8(_) => level.to_luma_alpha8().into(),
+        DynamicImage::ImageRgb8(_) => level.to_rgb8().into(),
+        DynamicImage::ImageRgba8(_) => level.to_rgba8().into(),
+        DynamicImage::ImageLuma16(_) => level.to_luma16().into(),
+        DynamicImage::ImageLumaA16(_) => level.to_luma_alpha16().into(),
+        DynamicImage::ImageRgb16(_) => level.to_rgb16().into(),
+        DynamicImage::ImageRgba16(_) => level.to_rgba16().into(),
+        DynamicImage::ImageRgb32F(_) => level.to_rgb32f().into(),
+        _ => level,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayAlphaImage, LumaA, Rgba, RgbaImage};
+
+    const FILTERS: [MipmapFilter; 3] = [
+        MipmapFilter::Box,
+        MipmapFilter::Lanczos,
+        MipmapFilter::Kaiser,
+    ];
+
+    fn options(filter: MipmapFilter) -> MipmapOptions {
+        MipmapOptions {
+            filter,
+            alpha_coverage: None,
+        }
+    }
+
+    #[test]
+    fn halves_odd_sizes_down_to_one() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(5, 3));
+        let sizes: Vec<_> = mip_chain(&image, &MipmapOptions::default(), false)
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect();
+        assert_eq!(sizes, [(2, 1), (1, 1)]);
+
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(16, 4));
+        let levels = mip_chain(&image, &MipmapOptions::default(), false);
+        let sizes: Vec<_> = levels
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect();
+        assert_eq!(sizes, [(8, 2), (4, 1), (2, 1), (1, 1)]);
+        assert!(levels
+            .iter()
+            .all(|level| matches!(level, DynamicImage::ImageRgba8(_))));
+    }
+
+    #[test]
+    fn weights_are_normalised() {
+        for filter in FILTERS {
+            for (source, destination) in [(5, 2), (16, 8), (7, 3), (2, 1)] {
+                for taps in weights(source, destination, filter) {
+                    let total: f32 = taps.iter().map(|&(_, weight)| weight).sum();
+                    assert!((total - 1.0).abs() < 1e-5, "{filter:?} {total}");
+                    assert!(taps.iter().all(|&(texel, _)| texel < source));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn constant_images_stay_constant() {
+        let color = Rgba([40, 120, 200, 180]);
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(13, 7, color));
+        for filter in FILTERS {
+            for srgb in [false, true] {
+                for level in mip_chain(&image, &options(filter), srgb) {
+                    let level = level.to_rgba8();
+                    assert!(
+                        level.pixels().all(|&pixel| pixel == color),
+                        "{filter:?} srgb {srgb}: {:?}",
+                        level.get_pixel(0, 0)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_round_trips() {
+        for index in 0..=255 {
+            let value = index as f32 / 255.0;
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-5);
+        }
+        assert_eq!(srgb_to_linear(0.0), 0.0);
+        assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn checkers_average_in_linear_light() {
+        let checker = RgbaImage::from_fn(4, 4, |x, y| match (x + y) % 2 {
+            0 => Rgba([0, 0, 0, 255]),
+            _ => Rgba([255, 255, 255, 255]),
+        });
+        let image = DynamicImage::ImageRgba8(checker);
+
+        let levels = mip_chain(&image, &options(MipmapFilter::Box), true);
+        let gamma_correct = levels[0].to_rgba8();
+        assert!(gamma_correct
+            .pixels()
+            .all(|pixel| (187..=189).contains(&pixel[0]) && pixel[3] == 255));
+
+        let levels = mip_chain(&image, &options(MipmapFilter::Box), false);
+        assert!(levels[0]
+            .to_rgba8()
+            .pixels()
+            .all(|pixel| (127..=128).contains(&pixel[0])));
+    }
+
+    // Thin vertical blades with soft edges, most of their alpha below the reference once
+    // they are averaged with the gaps between them
+    fn blades(size: u32) -> DynamicImage {
+        DynamicImage::ImageLumaA8(GrayAlphaImage::from_fn(size, size, |x, y| {
+            let edge = (x % 8) as f32 - 1.5;
+            let alpha = (1.0 - edge.abs() / 2.5).max(0.0) * (1.0 - y as f32 / size as f32 / 2.0);
+            LumaA([255, (alpha * 255.0) as u8])
+        }))
+    }
+
+    fn coverage(image: &DynamicImage, reference: f32) -> f32 {
+        alpha_coverage(&image.to_rgba32f(), reference)
+    }
+
+    #[test]
+    fn keeps_alpha_coverage() {
+        let image = blades(64);
+        let reference = 0.5;
+        let original = coverage(&image, reference);
+        assert!(original > 0.1);
+
+        let plain = mip_chain(&image, &options(MipmapFilter::Kaiser), false);
+        assert!(plain[2..]
+            .iter()
+            .any(|level| (coverage(level, reference) - original).abs() > 0.1));
+
+        let options = MipmapOptions {
+            filter: MipmapFilter::Kaiser,
+            alpha_coverage: Some(reference),
+        };
+        // 4x4 and above, smaller levels cannot get close to an arbitrary share
+        for level in mip_chain(&image, &options, false).iter().take(4) {
+            let kept = coverage(level, reference);
+            assert!(
+                (kept - original).abs() < 0.05,
+                "{}x{}: {kept} against {original}",
+                level.width(),
+                level.height()
+            );
+        }
+    }
+}
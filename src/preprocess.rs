@@ -0,0 +1,358 @@
+use crate::error::ShaderError;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// `#define`s injected into a shader, kept sorted so equal sets compare and hash equal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Defines(BTreeMap<String, String>);
+
+impl Defines {
+    pub fn new() -> Defines {
+        Defines::default()
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.0.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn with(mut self, name: &str, value: &str) -> Defines {
+        self.set(name, value);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+}
+
+/// A file that went into a `ShaderSource`, its index is the source string number used in
+/// the `#line` directives and reported back in driver logs.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+/// GLSL with `#include`s resolved and `#define`s injected, ready to compile.
+#[derive(Debug, Clone)]
+pub struct ShaderSource {
+    pub source: String,
+    files: Vec<SourceFile>,
+}
+
+impl ShaderSource {
+    /// Reads `path` and expands it.
+    ///
+    /// `#include "file"` is resolved relative to the including file and may nest, `#version`
+    /// lines in included files are dropped, and `defines` are placed right after the
+    /// `#version` line of `path`.
+    pub fn from_file(path: &Path, defines: &Defines) -> Result<ShaderSource, ShaderError> {
+        let mut expander = Expander {
+            files: Vec::new(),
+            stack: Vec::new(),
+            output: String::new(),
+            defines,
+        };
+        expander.expand(path)?;
+
+        Ok(ShaderSource {
+            source: expander.output,
+            files: expander.files,
+        })
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// The file a diagnostic points into.
+    pub fn file(&self, index: u32) -> Option<&SourceFile> {
+        self.files.get(index as usize)
+    }
+
+    /// Like `ShaderError::annotate`, but each diagnostic is shown in the file it came from.
+    pub fn annotate(&self, error: &ShaderError) -> String {
+        let diagnostics = error.diagnostics();
+        if diagnostics.is_empty() {
+            return error.to_string();
+        }
+
+        let mut output = String::new();
+        for diagnostic in diagnostics {
+            let annotated = match self.file(diagnostic.file) {
+                Some(file) => diagnostic.render(&file.path.display().to_string(), &file.text),
+                None => diagnostic.render(&diagnostic.file.to_string(), ""),
+            };
+            output.push_str(&annotated);
+        }
+        output
+    }
+}
+
+struct Expander<'a> {
+    files: Vec<SourceFile>,
+    stack: Vec<PathBuf>,
+    output: String,
+    defines: &'a Defines,
+}
+
+impl Expander<'_> {
+    fn expand(&mut self, path: &Path) -> Result<(), ShaderError> {
+        let is_root = self.files.is_empty();
+        let include_error = |message: String| ShaderError::Include {
+            path: path.to_path_buf(),
+            message,
+        };
+        let read_error = |error: std::io::Error| match is_root {
+            true => ShaderError::Io(error),
+            false => include_error(error.to_string()),
+        };
+
+        let canonical = path.canonicalize().map_err(read_error)?;
+        if let Some(start) = self.stack.iter().position(|file| *file == canonical) {
+            let cycle: Vec<String> = self.stack[start..]
+                .iter()
+                .chain([&canonical])
+                .map(|file| file.display().to_string())
+                .collect();
+            return Err(include_error(format!(
+                "include cycle: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        let text = std::fs::read_to_string(path).map_err(read_error)?;
+        let index = self.files.len();
+        self.files.push(SourceFile {
+            path: path.to_path_buf(),
+            text: text.clone(),
+        });
+        self.stack.push(canonical);
+
+        let has_version = text
+            .lines()
+            .any(|line| matches!(directive(line), Some(("version", _))));
+        if is_root && !has_version {
+            self.inject_defines(1, index);
+        } else if !is_root {
+            self.output.push_str(&format!("#line 1 {index}\n"));
+        }
+
+        for (number, line) in text.lines().enumerate() {
+            let line_number = number + 1;
+            match directive(line) {
+                Some(("version", _)) if is_root => {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                    self.inject_defines(line_number + 1, index);
+                }
+                // Keep the line count intact so the #line directives stay correct
+                Some(("version", _)) => self.output.push('\n'),
+                Some(("include", argument)) => {
+                    let name = argument
+                        .strip_prefix('"')
+                        .and_then(|argument| argument.strip_suffix('"'))
+                        .ok_or_else(|| {
+                            include_error(format!(
+                                "line {line_number}: expected #include \"file\", found {argument}"
+                            ))
+                        })?;
+                    let included = path.parent().unwrap_or(Path::new("")).join(name);
+                    self.expand(&included)?;
+                    self.output
+                        .push_str(&format!("#line {} {index}\n", line_number + 1));
+                }
+                _ => {
+                    self.output.push_str(line);
+                    self.output.push('\n');
+                }
+            }
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+
+    // Emit the defines, then put the line numbering back to where it was
+    fn inject_defines(&mut self, next_line: usize, index: usize) {
+        if self.defines.is_empty() {
+            return;
+        }
+        for (name, value) in self.defines.iter() {
+            self.output.push_str(&format!("#define {name} {value}\n"));
+        }
+        self.output
+            .push_str(&format!("#line {next_line} {index}\n"));
+    }
+}
+
+// Split a preprocessor line such as `#  include "a.glsl"` into ("include", "\"a.glsl\"")
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    Some((&rest[..end], rest[end..].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory holding `files`, removed again when dropped
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, files: &[(&str, &str)]) -> Fixture {
+            let directory = std::env::temp_dir()
+                .join(format!("opengl-preprocess-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&directory);
+            for (path, text) in files {
+                let path = directory.join(path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, text).unwrap();
+            }
+            Fixture(directory)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn expands_includes_with_line_directives() {
+        let fixture = Fixture::new(
+            "includes",
+            &[
+                (
+                    "main.glsl",
+                    "#version 330 core\n#include \"lib/light.glsl\"\nvoid main() {}\n",
+                ),
+                (
+                    "lib/light.glsl",
+                    "#version 330 core\n#include \"common.glsl\"\nfloat light;\n",
+                ),
+                ("lib/common.glsl", "const float PI = 3.14159;\n"),
+            ],
+        );
+        let source = ShaderSource::from_file(&fixture.path("main.glsl"), &Defines::new()).unwrap();
+
+        assert_eq!(
+            source.source,
+            "#version 330 core\n\
+             #line 1 1\n\
+             \n\
+             #line 1 2\n\
+             const float PI = 3.14159;\n\
+             #line 3 1\n\
+             float light;\n\
+             #line 3 0\n\
+             void main() {}\n"
+        );
+        let names: Vec<_> = source
+            .files()
+            .iter()
+            .map(|file| file.path.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(names, ["main.glsl", "light.glsl", "common.glsl"]);
+        assert_eq!(source.file(2).unwrap().text, "const float PI = 3.14159;\n");
+    }
+
+    #[test]
+    fn injects_defines_after_the_version() {
+        let fixture = Fixture::new(
+            "defines",
+            &[
+                (
+                    "versioned.glsl",
+                    "// header\n#version 330 core\nout vec4 color;\n",
+                ),
+                ("bare.glsl", "out vec4 color;\n"),
+            ],
+        );
+        let defines = Defines::new().with("USE_FOG", "1").with("LIGHTS", "4");
+
+        let source = ShaderSource::from_file(&fixture.path("versioned.glsl"), &defines).unwrap();
+        assert_eq!(
+            source.source,
+            "// header\n#version 330 core\n#define LIGHTS 4\n#define USE_FOG 1\n#line 3 0\n\
+             out vec4 color;\n"
+        );
+
+        let source = ShaderSource::from_file(&fixture.path("bare.glsl"), &defines).unwrap();
+        assert_eq!(
+            source.source,
+            "#define LIGHTS 4\n#define USE_FOG 1\n#line 1 0\nout vec4 color;\n"
+        );
+    }
+
+    #[test]
+    fn reports_include_errors() {
+        let fixture = Fixture::new(
+            "errors",
+            &[
+                ("a.glsl", "#include \"b.glsl\"\n"),
+                ("b.glsl", "#include \"a.glsl\"\n"),
+                ("missing.glsl", "#include \"nowhere.glsl\"\n"),
+                ("unquoted.glsl", "\n#include <c.glsl>\n"),
+            ],
+        );
+        let expand = |name| ShaderSource::from_file(&fixture.path(name), &Defines::new());
+
+        match expand("a.glsl") {
+            Err(ShaderError::Include { message, .. }) => {
+                assert!(message.starts_with("include cycle: "), "{message}");
+                assert!(message.ends_with("a.glsl"), "{message}");
+            }
+            other => panic!("expected an include cycle, got {other:?}"),
+        }
+        match expand("missing.glsl") {
+            Err(ShaderError::Include { path, .. }) => assert!(path.ends_with("nowhere.glsl")),
+            other => panic!("expected a missing include, got {other:?}"),
+        }
+        match expand("unquoted.glsl") {
+            Err(ShaderError::Include { message, .. }) => assert_eq!(
+                message,
+                "line 2: expected #include \"file\", found <c.glsl>"
+            ),
+            other => panic!("expected a malformed include, got {other:?}"),
+        }
+        assert!(matches!(expand("absent.glsl"), Err(ShaderError::Io(_))));
+    }
+
+    #[test]
+    fn maps_diagnostics_back_to_files() {
+        let fixture = Fixture::new(
+            "annotate",
+            &[
+                ("main.glsl", "#include \"lib.glsl\"\nvoid main() {}\n"),
+                ("lib.glsl", "float x = y;\n"),
+            ],
+        );
+        let source = ShaderSource::from_file(&fixture.path("main.glsl"), &Defines::new()).unwrap();
+        let error = ShaderError::Compile {
+            stage: crate::shader::ShaderStage::Fragment,
+            log: "1:1(11): error: `y' undeclared\n".to_string(),
+        };
+
+        let annotated = source.annotate(&error);
+        let lib = fixture.path("lib.glsl").display().to_string();
+        assert_eq!(
+            annotated,
+            format!("{lib}:1: error: `y' undeclared\n   1 | float x = y;\n     |           ^\n")
+        );
+    }
+}
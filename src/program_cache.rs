@@ -0,0 +1,177 @@
+use crate::context;
+use crate::preprocess::Defines;
+use crate::shader::{Shader, ShaderStage};
+use gl::types::*;
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::CStr;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+// Written before the binary format and the binary itself
+const MAGIC: &[u8; 4] = b"GLPB";
+
+/// A directory of linked program binaries, so programs are only compiled on the first launch.
+///
+/// Entries are keyed by the stage sources, the defines and the driver, so an updated driver
+/// or an edited shader simply misses the cache. A binary the driver rejects is deleted and
+/// the program is compiled from source instead. Needs OpenGL 4.1 or
+/// `GL_ARB_get_program_binary`, without it the cache is skipped.
+#[derive(Debug, Clone)]
+pub struct ProgramCache {
+    directory: PathBuf,
+}
+
+impl ProgramCache {
+    /// The directory is created when the first program is stored.
+    pub fn new(directory: &Path) -> ProgramCache {
+        ProgramCache {
+            directory: directory.to_path_buf(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Whether the driver can hand out program binaries at all.
+    pub fn is_supported() -> bool {
+        if !context::supports_version(4, 1) && !context::has_extension("GL_ARB_get_program_binary")
+        {
+            return false;
+        }
+
+        let mut formats = 0;
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+        }
+        formats > 0
+    }
+
+    /// Deletes every cached binary.
+    pub fn clear(&self) -> io::Result<()> {
+        let entries = match std::fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "bin") {
+                std::fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Hash everything that could make a binary differ. DefaultHasher may change between Rust
+    // releases, which only costs a recompile.
+    pub(crate) fn key(&self, stages: &[(ShaderStage, &CStr)], defines: &Defines) -> String {
+        let mut hasher = DefaultHasher::new();
+        for name in [
+            gl::VENDOR,
+            gl::RENDERER,
+            gl::VERSION,
+            gl::SHADING_LANGUAGE_VERSION,
+        ] {
+            driver_string(name).hash(&mut hasher);
+        }
+        for (stage, source) in stages {
+            stage.hash(&mut hasher);
+            source.hash(&mut hasher);
+        }
+        defines.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{key}.bin"))
+    }
+
+    /// Loads a cached program, deleting the entry when the driver no longer accepts it.
+    pub(crate) fn load(&self, key: &str) -> Option<Shader> {
+        let path = self.path(key);
+        let data = std::fs::read(&path).ok()?;
+
+        let linked = data
+            .strip_prefix(MAGIC)
+            .filter(|rest| rest.len() > 4)
+            .and_then(|rest| {
+                let format = GLenum::from_le_bytes(rest[..4].try_into().unwrap());
+                unsafe { load_binary(format, &rest[4..]) }
+            });
+        if linked.is_none() {
+            let _ = std::fs::remove_file(&path);
+        }
+        linked
+    }
+
+    /// Saves a program linked with `Shader::link_retrievable`.
+    pub(crate) fn store(&self, key: &str, shader: &Shader) -> io::Result<()> {
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramiv(shader.get(), gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if length <= 0 {
+            return Err(io::Error::other("the driver returned no program binary"));
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let mut written = 0;
+        let mut format = 0;
+        unsafe {
+            gl::GetProgramBinary(
+                shader.get(),
+                length,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut _,
+            );
+        }
+        binary.truncate(written as usize);
+
+        let mut data = Vec::with_capacity(MAGIC.len() + 4 + binary.len());
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&format.to_le_bytes());
+        data.extend_from_slice(&binary);
+
+        // Write next to the entry and rename, so a crash never leaves half a binary behind
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.path(key);
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, data)?;
+        std::fs::rename(&temporary, &path)
+    }
+}
+
+unsafe fn load_binary(format: GLenum, binary: &[u8]) -> Option<Shader> {
+    // Wrap the program right away so it is deleted if the binary is rejected
+    let shader = Shader::from_raw(gl::CreateProgram());
+    gl::ProgramBinary(
+        shader.get(),
+        format,
+        binary.as_ptr() as *const _,
+        binary.len() as GLsizei,
+    );
+
+    let mut success = gl::FALSE as GLint;
+    gl::GetProgramiv(shader.get(), gl::LINK_STATUS, &mut success);
+    if success != gl::TRUE as GLint {
+        return None;
+    }
+
+    // The uniforms can only be listed once the program is linked
+    Some(Shader::from_raw(shader.into_raw()))
+}
+
+fn driver_string(name: GLenum) -> String {
+    unsafe {
+        let string = gl::GetString(name);
+        if string.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(string as *const _)
+            .to_string_lossy()
+            .into_owned()
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::ShaderError;
+use crate::preprocess::{Defines, ShaderSource};
+use crate::shader::{Shader, ShaderStage};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// Every file a program was built from, with the modification time it had back then
+type Watched = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// A shader built from files that is rebuilt whenever one of the files changes.
+///
+/// The files, including everything they `#include`, are polled for a newer modification
+/// time, so no file watcher is needed. When a rebuild fails the previous program stays in use
+/// and the error is printed to stderr.
+pub struct ReloadableShader {
+    vertex_path: PathBuf,
+    fragment_path: PathBuf,
+    defines: Defines,
+    watched: Watched,
+    shader: Shader,
+    last_error: Option<ShaderError>,
+    poll_interval: Duration,
+    last_poll: Instant,
+}
+
+impl ReloadableShader {
+    pub fn from_file(
+        vertex_source_path: &Path,
+        fragment_source_path: &Path,
+    ) -> Result<ReloadableShader, ShaderError> {
+        ReloadableShader::from_file_with_defines(
+            vertex_source_path,
+            fragment_source_path,
+            &Defines::new(),
+        )
+    }
+
+    pub fn from_file_with_defines(
+        vertex_source_path: &Path,
+        fragment_source_path: &Path,
+        defines: &Defines,
+    ) -> Result<ReloadableShader, ShaderError> {
+        let (shader, watched) =
+            build(vertex_source_path, fragment_source_path, defines).map_err(|(error, _)| error)?;
+
+        Ok(ReloadableShader {
+            vertex_path: vertex_source_path.to_path_buf(),
+            fragment_path: fragment_source_path.to_path_buf(),
+            defines: defines.clone(),
+            watched,
+            shader,
+            last_error: None,
+            poll_interval: Duration::from_millis(250),
+            last_poll: Instant::now(),
+        })
+    }
+
+    /// How often `reload_if_changed` looks at the files, 250ms by default.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> ReloadableShader {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn shader(&self) -> &Shader {
+        &self.shader
+    }
+
+    pub fn get(&self) -> u32 {
+        self.shader.get()
+    }
+
+    /// The error of the last failed rebuild, cleared once a rebuild succeeds.
+    pub fn last_error(&self) -> Option<&ShaderError> {
+        self.last_error.as_ref()
+    }
+
+    /// Rebuilds the program if a source file changed, call this once per frame.
+    ///
+    /// Returns true when the program was replaced. Uniforms live in the program, so they have
+    /// to be set again afterwards.
+    pub fn reload_if_changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < self.poll_interval {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let modified: Vec<Option<SystemTime>> = self
+            .watched
+            .iter()
+            .map(|(path, _)| modified_time(path))
+            .collect();
+
+        // Editors often delete and recreate files when saving, wait until all of them exist
+        let unchanged = self
+            .watched
+            .iter()
+            .zip(&modified)
+            .all(|((_, before), now)| before == now);
+        if unchanged || modified.contains(&None) {
+            return false;
+        }
+        for ((_, before), now) in self.watched.iter_mut().zip(modified) {
+            *before = now;
+        }
+
+        self.reload()
+    }
+
+    /// Rebuilds the program unconditionally, keeping the old one if it fails.
+    pub fn reload(&mut self) -> bool {
+        match build(&self.vertex_path, &self.fragment_path, &self.defines) {
+            Ok((shader, watched)) => {
+                self.shader = shader;
+                self.watched = watched;
+                self.last_error = None;
+                true
+            }
+            Err((error, description)) => {
+                eprintln!("Shader reload failed: {description}");
+                self.last_error = Some(error);
+                false
+            }
+        }
+    }
+}
+
+// Build the program and list every file it was made from, on failure also describe the
+// error against the file it came from
+fn build(
+    vertex_path: &Path,
+    fragment_path: &Path,
+    defines: &Defines,
+) -> Result<(Shader, Watched), (ShaderError, String)> {
+    let describe = |error: ShaderError| {
+        let description = error.to_string();
+        (error, description)
+    };
+    let vertex = ShaderSource::from_file(vertex_path, defines).map_err(describe)?;
+    let fragment = ShaderSource::from_file(fragment_path, defines).map_err(describe)?;
+
+    let shader = Shader::from_source(&vertex, &fragment).map_err(|error| {
+        let description = match &error {
+            ShaderError::Compile {
+                stage: ShaderStage::Vertex,
+                ..
+            } => vertex.annotate(&error),
+            ShaderError::Compile {
+                stage: ShaderStage::Fragment,
+                ..
+            } => fragment.annotate(&error),
+            _ => error.to_string(),
+        };
+        (error, description)
+    })?;
+
+    let mut watched = Watched::new();
+    for file in vertex.files().iter().chain(fragment.files()) {
+        if !watched.iter().any(|(path, _)| *path == file.path) {
+            watched.push((file.path.clone(), modified_time(&file.path)));
+        }
+    }
+
+    Ok((shader, watched))
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
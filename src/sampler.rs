@@ -0,0 +1,435 @@
+use crate::context;
+use crate::uniform::TextureUnit;
+use gl::types::*;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// Core since 4.6 with the same values as GL_EXT_texture_filter_anisotropic, the bindings
+// stop at 4.5
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Wrap {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    /// Reads outside the texture return `SamplerDesc::border_color`
+    ClampToBorder,
+}
+
+impl Wrap {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+            Wrap::ClampToBorder => gl::CLAMP_TO_BORDER,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+impl Filter {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// The test a depth comparison sampler (`sampler2DShadow`) runs against the reference value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            CompareFunction::Never => gl::NEVER,
+            CompareFunction::Less => gl::LESS,
+            CompareFunction::Equal => gl::EQUAL,
+            CompareFunction::LessEqual => gl::LEQUAL,
+            CompareFunction::Greater => gl::GREATER,
+            CompareFunction::NotEqual => gl::NOTEQUAL,
+            CompareFunction::GreaterEqual => gl::GEQUAL,
+            CompareFunction::Always => gl::ALWAYS,
+        }
+    }
+}
+
+/// Everything about how a texture is sampled, separate from the texture itself.
+///
+/// The default is trilinear filtering with repeating coordinates.
+#[derive(Debug, Clone, Copy)]
+pub struct SamplerDesc {
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    pub wrap_r: Wrap,
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// How mipmaps are chosen and blended, `None` samples the base level only
+    pub mip_filter: Option<Filter>,
+    /// 1 turns anisotropic filtering off, clamped to what the driver supports
+    pub max_anisotropy: f32,
+    pub border_color: [f32; 4],
+    pub lod_bias: f32,
+    /// Turns the sampler into a depth comparison sampler
+    pub compare: Option<CompareFunction>,
+}
+
+impl Default for SamplerDesc {
+    fn default() -> SamplerDesc {
+        SamplerDesc {
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            wrap_r: Wrap::Repeat,
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mip_filter: Some(Filter::Linear),
+            max_anisotropy: 1.0,
+            border_color: [0.0; 4],
+            lod_bias: 0.0,
+            compare: None,
+        }
+    }
+}
+
+impl SamplerDesc {
+    /// Blocky magnification and no blending between mipmaps, for pixel art.
+    pub fn nearest() -> SamplerDesc {
+        SamplerDesc::default().filter(Filter::Nearest)
+    }
+
+    /// Sets the wrap mode of every coordinate.
+    pub fn wrap(mut self, wrap: Wrap) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self.wrap_r = wrap;
+        self
+    }
+
+    /// Sets the min, mag and mip filter, leaving mipmaps off if they already are.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.min_filter = filter;
+        self.mag_filter = filter;
+        self.mip_filter = self.mip_filter.map(|_| filter);
+        self
+    }
+
+    pub fn anisotropy(mut self, max_anisotropy: f32) -> Self {
+        self.max_anisotropy = max_anisotropy;
+        self
+    }
+
+    fn gl_min_filter(&self) -> GLenum {
+        match (self.min_filter, self.mip_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        }
+    }
+
+    // Floats compared by their bits, so descriptions can key a map
+    fn key(&self) -> impl Eq + Hash {
+        (
+            (self.wrap_s, self.wrap_t, self.wrap_r),
+            (self.min_filter, self.mag_filter, self.mip_filter),
+            self.max_anisotropy.to_bits(),
+            self.border_color.map(f32::to_bits),
+            self.lod_bias.to_bits(),
+            self.compare,
+        )
+    }
+}
+
+impl PartialEq for SamplerDesc {
+    fn eq(&self, other: &SamplerDesc) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SamplerDesc {}
+
+impl Hash for SamplerDesc {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// Owns a sampler object, which is deleted when dropped.
+///
+/// A sampler bound to a texture unit overrides the sampling parameters of whatever texture
+/// is bound there, so one sampler serves any number of textures.
+pub struct Sampler {
+    sampler: GLuint,
+    desc: SamplerDesc,
+}
+
+impl Sampler {
+    pub fn new(desc: &SamplerDesc) -> Sampler {
+        let mut sampler = 0;
+        unsafe {
+            gl::GenSamplers(1, &mut sampler);
+            gl::SamplerParameteri(sampler, gl::TEXTURE_WRAP_S, desc.wrap_s.gl_enum() as GLint);
+            gl::SamplerParameteri(sampler, gl::TEXTURE_WRAP_T, desc.wrap_t.gl_enum() as GLint);
+            gl::SamplerParameteri(sampler, gl::TEXTURE_WRAP_R, desc.wrap_r.gl_enum() as GLint);
+            gl::SamplerParameteri(
+                sampler,
+                gl::TEXTURE_MIN_FILTER,
+                desc.gl_min_filter() as GLint,
+            );
+            gl::SamplerParameteri(
+                sampler,
+                gl::TEXTURE_MAG_FILTER,
+                desc.mag_filter.gl_enum() as GLint,
+            );
+            gl::SamplerParameterfv(
+                sampler,
+                gl::TEXTURE_BORDER_COLOR,
+                desc.border_color.as_ptr(),
+            );
+            gl::SamplerParameterf(sampler, gl::TEXTURE_LOD_BIAS, desc.lod_bias);
+
+            if let Some(compare) = desc.compare {
+                gl::SamplerParameteri(
+                    sampler,
+                    gl::TEXTURE_COMPARE_MODE,
+                    gl::COMPARE_REF_TO_TEXTURE as GLint,
+                );
+                gl::SamplerParameteri(
+                    sampler,
+                    gl::TEXTURE_COMPARE_FUNC,
+                    compare.gl_enum() as GLint,
+                );
+            }
+
+            // Without support the request is ignored rather than raising an error
+            if desc.max_anisotropy > 1.0 && supports_anisotropy() {
+                let mut max = 1.0;
+                gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+                gl::SamplerParameterf(
+                    sampler,
+                    TEXTURE_MAX_ANISOTROPY,
+                    desc.max_anisotropy.min(max),
+                );
+            }
+        }
+
+        Sampler {
+            sampler,
+            desc: *desc,
+        }
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.sampler
+    }
+
+    pub fn desc(&self) -> &SamplerDesc {
+        &self.desc
+    }
+
+    pub fn bind(&self, unit: TextureUnit) {
+        unsafe {
+            gl::BindSampler(unit.0, self.sampler);
+        }
+    }
+
+    /// Goes back to sampling with the parameters of the texture bound to `unit`.
+    pub fn unbind(unit: TextureUnit) {
+        unsafe {
+            gl::BindSampler(unit.0, 0);
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSamplers(1, &self.sampler);
+        }
+    }
+}
+
+fn supports_anisotropy() -> bool {
+    context::supports_version(4, 6)
+        || context::has_extension("GL_ARB_texture_filter_anisotropic")
+        || context::has_extension("GL_EXT_texture_filter_anisotropic")
+}
+
+/// Creates each distinct sampler once, however many textures ask for it.
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerDesc, Sampler>,
+}
+
+impl SamplerCache {
+    pub fn new() -> SamplerCache {
+        SamplerCache::default()
+    }
+
+    /// The sampler for `desc`, created on first use.
+    pub fn get(&mut self, desc: &SamplerDesc) -> &Sampler {
+        self.samplers
+            .entry(*desc)
+            .or_insert_with(|| Sampler::new(desc))
+    }
+
+    /// Number of distinct samplers created.
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+
+    /// Deletes every sampler.
+    pub fn clear(&mut self) {
+        self.samplers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash(desc: &SamplerDesc) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        desc.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn min_filters() {
+        let cases = [
+            (Filter::Nearest, None, gl::NEAREST),
+            (Filter::Linear, None, gl::LINEAR),
+            (
+                Filter::Nearest,
+                Some(Filter::Nearest),
+                gl::NEAREST_MIPMAP_NEAREST,
+            ),
+            (
+                Filter::Linear,
+                Some(Filter::Nearest),
+                gl::LINEAR_MIPMAP_NEAREST,
+            ),
+            (
+                Filter::Nearest,
+                Some(Filter::Linear),
+                gl::NEAREST_MIPMAP_LINEAR,
+            ),
+            (
+                Filter::Linear,
+                Some(Filter::Linear),
+                gl::LINEAR_MIPMAP_LINEAR,
+            ),
+        ];
+        for (min_filter, mip_filter, expected) in cases {
+            let desc = SamplerDesc {
+                min_filter,
+                mip_filter,
+                ..SamplerDesc::default()
+            };
+            assert_eq!(
+                desc.gl_min_filter(),
+                expected,
+                "{min_filter:?} {mip_filter:?}"
+            );
+        }
+        assert_eq!(
+            SamplerDesc::default().gl_min_filter(),
+            gl::LINEAR_MIPMAP_LINEAR
+        );
+        assert_eq!(
+            SamplerDesc::nearest().gl_min_filter(),
+            gl::NEAREST_MIPMAP_NEAREST
+        );
+        assert_eq!(SamplerDesc::nearest().mag_filter.gl_enum(), gl::NEAREST);
+    }
+
+    #[test]
+    fn filter_keeps_mipmaps_off() {
+        let desc = SamplerDesc {
+            mip_filter: None,
+            ..SamplerDesc::default()
+        }
+        .filter(Filter::Nearest);
+        assert_eq!(desc.mip_filter, None);
+        assert_eq!(desc.gl_min_filter(), gl::NEAREST);
+    }
+
+    #[test]
+    fn enums() {
+        let wraps = [
+            (Wrap::Repeat, gl::REPEAT),
+            (Wrap::MirroredRepeat, gl::MIRRORED_REPEAT),
+            (Wrap::ClampToEdge, gl::CLAMP_TO_EDGE),
+            (Wrap::ClampToBorder, gl::CLAMP_TO_BORDER),
+        ];
+        for (wrap, expected) in wraps {
+            assert_eq!(wrap.gl_enum(), expected);
+        }
+        let desc = SamplerDesc::default().wrap(Wrap::ClampToEdge);
+        assert_eq!(
+            [desc.wrap_s, desc.wrap_t, desc.wrap_r],
+            [Wrap::ClampToEdge; 3]
+        );
+
+        let compares = [
+            (CompareFunction::Never, gl::NEVER),
+            (CompareFunction::Less, gl::LESS),
+            (CompareFunction::Equal, gl::EQUAL),
+            (CompareFunction::LessEqual, gl::LEQUAL),
+            (CompareFunction::Greater, gl::GREATER),
+            (CompareFunction::NotEqual, gl::NOTEQUAL),
+            (CompareFunction::GreaterEqual, gl::GEQUAL),
+            (CompareFunction::Always, gl::ALWAYS),
+        ];
+        for (compare, expected) in compares {
+            assert_eq!(compare.gl_enum(), expected);
+        }
+    }
+
+    #[test]
+    fn descriptions_key_by_value() {
+        let desc = SamplerDesc::default().anisotropy(8.0);
+        assert_eq!(desc, SamplerDesc::default().anisotropy(8.0));
+        assert_eq!(hash(&desc), hash(&SamplerDesc::default().anisotropy(8.0)));
+        assert_ne!(desc, SamplerDesc::default());
+        assert_ne!(
+            desc,
+            SamplerDesc {
+                border_color: [1.0; 4],
+                ..desc
+            }
+        );
+
+        // Compared by their bits, so a NaN still finds its own sampler
+        let nan = SamplerDesc {
+            lod_bias: f32::NAN,
+            ..desc
+        };
+        assert_eq!(nan, nan);
+    }
+}
@@ -0,0 +1,572 @@
+use crate::context;
+use crate::error::{AttributeError, BlockError, ShaderError, UniformError};
+use crate::introspection::{self, ActiveAttribute, ActiveUniform, ActiveUniformBlock};
+use crate::preprocess::{Defines, ShaderSource};
+use crate::program_cache::ProgramCache;
+use crate::std140::Std140;
+use crate::uniform::{self, Uniform, UniformSlot};
+use crate::vertex::Vertex;
+use gl::types::*;
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderStage {
+    Vertex,
+    TessControl,
+    TessEvaluation,
+    Geometry,
+    Fragment,
+    Compute,
+}
+
+impl ShaderStage {
+    pub fn gl_enum(self) -> GLenum {
+        match self {
+            ShaderStage::Vertex => gl::VERTEX_SHADER,
+            ShaderStage::TessControl => gl::TESS_CONTROL_SHADER,
+            ShaderStage::TessEvaluation => gl::TESS_EVALUATION_SHADER,
+            ShaderStage::Geometry => gl::GEOMETRY_SHADER,
+            ShaderStage::Fragment => gl::FRAGMENT_SHADER,
+            ShaderStage::Compute => gl::COMPUTE_SHADER,
+        }
+    }
+
+    /// The lowest context version that has this stage in core.
+    pub fn required_version(self) -> (u32, u32) {
+        match self {
+            ShaderStage::Vertex | ShaderStage::Fragment => (2, 0),
+            ShaderStage::Geometry => (3, 2),
+            ShaderStage::TessControl | ShaderStage::TessEvaluation => (4, 0),
+            ShaderStage::Compute => (4, 3),
+        }
+    }
+
+    // Fail early with a readable error instead of a driver complaint about an unknown enum
+    pub(crate) fn check_supported(self) -> Result<(), ShaderError> {
+        let available = context::version();
+        if available < self.required_version() {
+            return Err(ShaderError::Unsupported {
+                stage: self,
+                required: self.required_version(),
+                available,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ShaderStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderStage::Vertex => write!(f, "vertex"),
+            ShaderStage::TessControl => write!(f, "tessellation control"),
+            ShaderStage::TessEvaluation => write!(f, "tessellation evaluation"),
+            ShaderStage::Geometry => write!(f, "geometry"),
+            ShaderStage::Fragment => write!(f, "fragment"),
+            ShaderStage::Compute => write!(f, "compute"),
+        }
+    }
+}
+
+/// Owns a linked shader program, which is deleted when dropped.
+pub struct Shader {
+    shader_program_id: u32,
+    uniforms: HashMap<String, UniformSlot>,
+}
+
+impl Shader {
+    pub fn get(&self) -> u32 {
+        self.shader_program_id
+    }
+
+    /// Takes ownership of an existing program, which is deleted on drop.
+    ///
+    /// # Safety
+    /// `shader_program_id` must be a valid program that nothing else deletes.
+    pub unsafe fn from_raw(shader_program_id: u32) -> Shader {
+        Shader {
+            shader_program_id,
+            uniforms: uniform::uniform_slots(shader_program_id),
+        }
+    }
+
+    /// Gives up ownership of the program, the caller has to delete it.
+    pub fn into_raw(mut self) -> u32 {
+        std::mem::take(&mut self.shader_program_id)
+    }
+
+    /// Sets a uniform by name, leaving the program in use.
+    ///
+    /// Locations are looked up once when the program is linked, so this is cheap enough to
+    /// call every frame.
+    pub fn set_uniform<T: Uniform>(&self, name: &str, value: T) -> Result<(), UniformError> {
+        let slot = self
+            .uniforms
+            .get(name)
+            .ok_or_else(|| UniformError::Unknown {
+                name: name.to_string(),
+            })?;
+        if !T::accepts(slot.gl_type) {
+            return Err(UniformError::TypeMismatch {
+                name: name.to_string(),
+                gl_type: slot.gl_type,
+                value_type: std::any::type_name::<T>(),
+            });
+        }
+
+        unsafe {
+            gl::UseProgram(self.shader_program_id);
+            value.set(slot.location);
+        }
+        Ok(())
+    }
+
+    pub fn active_uniforms(&self) -> Vec<ActiveUniform> {
+        unsafe { introspection::active_uniforms(self.shader_program_id) }
+    }
+
+    pub fn active_attributes(&self) -> Vec<ActiveAttribute> {
+        unsafe { introspection::active_attributes(self.shader_program_id) }
+    }
+
+    pub fn active_uniform_blocks(&self) -> Vec<ActiveUniformBlock> {
+        unsafe { introspection::active_uniform_blocks(self.shader_program_id) }
+    }
+
+    /// Checks that every vertex input of the program is provided by `layout`, given as
+    /// `(location, gl_type)` pairs such as `(0, gl::FLOAT_VEC2)`.
+    pub fn validate_attributes(&self, layout: &[(GLuint, GLenum)]) -> Result<(), AttributeError> {
+        for attribute in self.active_attributes() {
+            let Some(location) = attribute.location else {
+                continue;
+            };
+
+            match layout.iter().find(|(provided, _)| *provided == location) {
+                None => {
+                    return Err(AttributeError::Missing {
+                        name: attribute.name,
+                        location,
+                    })
+                }
+                Some(&(_, gl_type)) if gl_type != attribute.gl_type => {
+                    return Err(AttributeError::TypeMismatch {
+                        name: attribute.name,
+                        location,
+                        expected: attribute.gl_type,
+                        found: gl_type,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the vertex inputs match the attributes of `T`, see `validate_attributes`.
+    pub fn validate_vertex<T: Vertex>(&self) -> Result<(), AttributeError> {
+        let layout: Vec<(GLuint, GLenum)> = T::attributes()
+            .iter()
+            .map(|attribute| (attribute.location, attribute.glsl_type()))
+            .collect();
+        self.validate_attributes(&layout)
+    }
+
+    /// Points the uniform block `block_name` at a uniform buffer binding, after checking
+    /// that the std140 layout of `T` matches the one the program was linked with.
+    pub fn bind_uniform_block<T: Std140>(
+        &self,
+        block_name: &str,
+        binding: GLuint,
+    ) -> Result<(), BlockError> {
+        let block_error = || BlockError::Unknown {
+            block: block_name.to_string(),
+        };
+        let block = self
+            .active_uniform_blocks()
+            .into_iter()
+            .find(|block| block.name == block_name)
+            .ok_or_else(block_error)?;
+
+        // Drivers may or may not count the padding after the last member, which std140 rounds
+        // up to 16 bytes
+        let padded = |size: usize| size.next_multiple_of(16);
+        if padded(block.data_size as usize) != padded(T::SIZE) {
+            return Err(BlockError::SizeMismatch {
+                block: block_name.to_string(),
+                program_size: block.data_size as usize,
+                rust_size: T::SIZE,
+            });
+        }
+
+        let mut members = Vec::new();
+        T::std140_members("", 0, &mut members);
+        let prefix = format!("{block_name}.");
+        for uniform in self.active_uniforms() {
+            let (Some(index), Some(program_offset)) = (uniform.block_index, uniform.block_offset)
+            else {
+                continue;
+            };
+            if index != block.index {
+                continue;
+            }
+
+            // Blocks with an instance name report their members as `Block.member`
+            let member = uniform.name.strip_prefix(&prefix).unwrap_or(&uniform.name);
+            let rust_offset = members
+                .iter()
+                .find(|(name, _)| name == member)
+                .map(|&(_, offset)| offset)
+                .ok_or_else(|| BlockError::MissingMember {
+                    block: block_name.to_string(),
+                    member: member.to_string(),
+                })?;
+            if rust_offset != program_offset as usize {
+                return Err(BlockError::OffsetMismatch {
+                    block: block_name.to_string(),
+                    member: member.to_string(),
+                    program_offset: program_offset as usize,
+                    rust_offset,
+                });
+            }
+        }
+
+        unsafe {
+            gl::UniformBlockBinding(self.shader_program_id, block.index, binding);
+        }
+        Ok(())
+    }
+
+    /// Points the shader storage block `block_name` at a storage buffer binding, needs
+    /// OpenGL 4.3.
+    pub fn bind_storage_block(&self, block_name: &str, binding: GLuint) -> Result<(), BlockError> {
+        let name = CString::new(block_name).map_err(|_| BlockError::Unknown {
+            block: block_name.to_string(),
+        })?;
+        unsafe {
+            let index = gl::GetProgramResourceIndex(
+                self.shader_program_id,
+                gl::SHADER_STORAGE_BLOCK,
+                name.as_ptr(),
+            );
+            if index == gl::INVALID_INDEX {
+                return Err(BlockError::Unknown {
+                    block: block_name.to_string(),
+                });
+            }
+            gl::ShaderStorageBlockBinding(self.shader_program_id, index, binding);
+        }
+        Ok(())
+    }
+
+    pub fn from_cstr(vertex_source: &CStr, fragment_source: &CStr) -> Result<Shader, ShaderError> {
+        Shader::new(vertex_source, fragment_source)
+    }
+
+    /// Builds a program from files, resolving `#include`s on the way.
+    pub fn from_file(
+        vertex_source_path: &Path,
+        fragment_source_path: &Path,
+    ) -> Result<Shader, ShaderError> {
+        Shader::from_file_with_defines(vertex_source_path, fragment_source_path, &Defines::new())
+    }
+
+    pub fn from_file_with_defines(
+        vertex_source_path: &Path,
+        fragment_source_path: &Path,
+        defines: &Defines,
+    ) -> Result<Shader, ShaderError> {
+        let vertex_source = ShaderSource::from_file(vertex_source_path, defines)?;
+        let fragment_source = ShaderSource::from_file(fragment_source_path, defines)?;
+
+        Shader::from_source(&vertex_source, &fragment_source)
+    }
+
+    pub fn from_source(
+        vertex_source: &ShaderSource,
+        fragment_source: &ShaderSource,
+    ) -> Result<Shader, ShaderError> {
+        let vertex_source = CString::new(vertex_source.source.as_str())?;
+        let fragment_source = CString::new(fragment_source.source.as_str())?;
+
+        Shader::new(&vertex_source, &fragment_source)
+    }
+
+    fn new(vertex_source: &CStr, fragment_source: &CStr) -> Result<Shader, ShaderError> {
+        Shader::link(&[
+            (ShaderStage::Vertex, vertex_source),
+            (ShaderStage::Fragment, fragment_source),
+        ])
+    }
+
+    pub(crate) fn link(stages: &[(ShaderStage, &CStr)]) -> Result<Shader, ShaderError> {
+        Shader::link_program(stages, false)
+    }
+
+    /// Links with `PROGRAM_BINARY_RETRIEVABLE_HINT` set, so the binary can be cached.
+    pub(crate) fn link_retrievable(stages: &[(ShaderStage, &CStr)]) -> Result<Shader, ShaderError> {
+        Shader::link_program(stages, true)
+    }
+
+    fn link_program(
+        stages: &[(ShaderStage, &CStr)],
+        retrievable: bool,
+    ) -> Result<Shader, ShaderError> {
+        let shader_objects = stages
+            .iter()
+            .map(|&(stage, source)| ShaderObject::compile(stage, source))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        unsafe {
+            // Wrap the program right away so it is deleted if linking fails
+            let mut shader = Shader::from_raw(gl::CreateProgram());
+            for shader_object in &shader_objects {
+                gl::AttachShader(shader.get(), shader_object.0);
+            }
+            if retrievable {
+                gl::ProgramParameteri(
+                    shader.get(),
+                    gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                    gl::TRUE as GLint,
+                );
+            }
+            gl::LinkProgram(shader.get());
+
+            let mut success = gl::FALSE as GLint;
+            gl::GetProgramiv(shader.get(), gl::LINK_STATUS, &mut success);
+            if success != gl::TRUE as GLint {
+                let log = info_log(shader.get(), gl::GetProgramiv, gl::GetProgramInfoLog);
+                return Err(ShaderError::Link { log });
+            }
+
+            // The shader objects are no longer needed once the program is linked
+            for shader_object in &shader_objects {
+                gl::DetachShader(shader.get(), shader_object.0);
+            }
+
+            shader.uniforms = uniform::uniform_slots(shader.get());
+            Ok(shader)
+        }
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        if self.shader_program_id != 0 {
+            unsafe {
+                gl::DeleteProgram(self.shader_program_id);
+            }
+        }
+    }
+}
+
+enum StageSource {
+    Source(CString),
+    File(PathBuf),
+}
+
+/// Builds a program from any mix of the vertex, tessellation, geometry and fragment stages.
+///
+/// Stages newer than the current context are rejected with `ShaderError::Unsupported`, so
+/// ask GLFW for a 3.2 context for geometry shaders and 4.0 for tessellation.
+#[derive(Default)]
+pub struct ShaderBuilder {
+    stages: Vec<(ShaderStage, StageSource)>,
+    defines: Defines,
+    cache: Option<ProgramCache>,
+}
+
+impl ShaderBuilder {
+    pub fn new() -> ShaderBuilder {
+        ShaderBuilder::default()
+    }
+
+    pub fn stage(mut self, stage: ShaderStage, source: &CStr) -> ShaderBuilder {
+        self.stages
+            .push((stage, StageSource::Source(source.to_owned())));
+        self
+    }
+
+    /// Adds a stage read from a file, which goes through the preprocessor.
+    pub fn stage_file(mut self, stage: ShaderStage, path: &Path) -> ShaderBuilder {
+        self.stages
+            .push((stage, StageSource::File(path.to_path_buf())));
+        self
+    }
+
+    /// Defines injected into every stage added with `stage_file`.
+    pub fn defines(mut self, defines: &Defines) -> ShaderBuilder {
+        self.defines = defines.clone();
+        self
+    }
+
+    /// Loads the linked program from `cache` when possible, and stores it there otherwise.
+    pub fn cache(mut self, cache: &ProgramCache) -> ShaderBuilder {
+        self.cache = Some(cache.clone());
+        self
+    }
+
+    pub fn build(self) -> Result<Shader, ShaderError> {
+        for (index, (stage, _)) in self.stages.iter().enumerate() {
+            if *stage == ShaderStage::Compute {
+                return Err(ShaderError::InvalidPipeline(
+                    "compute shaders are built with ComputeProgram".to_string(),
+                ));
+            }
+            if self.stages[..index].iter().any(|(other, _)| other == stage) {
+                return Err(ShaderError::InvalidPipeline(format!(
+                    "{stage} stage is given more than once"
+                )));
+            }
+            stage.check_supported()?;
+        }
+
+        let has = |stage| self.stages.iter().any(|(other, _)| *other == stage);
+        if !has(ShaderStage::Vertex) {
+            return Err(ShaderError::InvalidPipeline(
+                "a vertex stage is required".to_string(),
+            ));
+        }
+        if has(ShaderStage::TessControl) && !has(ShaderStage::TessEvaluation) {
+            return Err(ShaderError::InvalidPipeline(
+                "a tessellation control stage needs a tessellation evaluation stage".to_string(),
+            ));
+        }
+
+        let mut sources = Vec::with_capacity(self.stages.len());
+        for (stage, source) in &self.stages {
+            let source = match source {
+                StageSource::Source(source) => source.clone(),
+                StageSource::File(path) => {
+                    CString::new(ShaderSource::from_file(path, &self.defines)?.source)?
+                }
+            };
+            sources.push((*stage, source));
+        }
+
+        let stages: Vec<(ShaderStage, &CStr)> = sources
+            .iter()
+            .map(|(stage, source)| (*stage, source.as_c_str()))
+            .collect();
+
+        match &self.cache {
+            Some(cache) if ProgramCache::is_supported() => {
+                let key = cache.key(&stages, &self.defines);
+                if let Some(shader) = cache.load(&key) {
+                    return Ok(shader);
+                }
+
+                let shader = Shader::link_retrievable(&stages)?;
+                // A failed write only costs a compile on the next launch
+                let _ = cache.store(&key, &shader);
+                Ok(shader)
+            }
+            _ => Shader::link(&stages),
+        }
+    }
+}
+
+/// Programs built from the same pair of files, compiled once per set of defines.
+pub struct ShaderVariants {
+    vertex_path: PathBuf,
+    fragment_path: PathBuf,
+    variants: HashMap<Defines, Shader>,
+    cache: Option<ProgramCache>,
+}
+
+impl ShaderVariants {
+    pub fn new(vertex_source_path: &Path, fragment_source_path: &Path) -> ShaderVariants {
+        ShaderVariants {
+            vertex_path: vertex_source_path.to_path_buf(),
+            fragment_path: fragment_source_path.to_path_buf(),
+            variants: HashMap::new(),
+            cache: None,
+        }
+    }
+
+    /// Keeps the linked variants in `cache` across launches.
+    pub fn with_cache(mut self, cache: &ProgramCache) -> ShaderVariants {
+        self.cache = Some(cache.clone());
+        self
+    }
+
+    /// Returns the variant for `defines`, compiling it on first use.
+    pub fn get(&mut self, defines: &Defines) -> Result<&Shader, ShaderError> {
+        if !self.variants.contains_key(defines) {
+            let mut builder = ShaderBuilder::new()
+                .stage_file(ShaderStage::Vertex, &self.vertex_path)
+                .stage_file(ShaderStage::Fragment, &self.fragment_path)
+                .defines(defines);
+            if let Some(cache) = &self.cache {
+                builder = builder.cache(cache);
+            }
+            let shader = builder.build()?;
+            self.variants.insert(defines.clone(), shader);
+        }
+
+        Ok(&self.variants[defines])
+    }
+
+    /// Drops every compiled variant, so they are rebuilt from the files on next use.
+    pub fn clear(&mut self) {
+        self.variants.clear();
+    }
+}
+
+// Owns a single compiled shader stage, which is deleted when dropped
+struct ShaderObject(u32);
+
+impl ShaderObject {
+    fn compile(stage: ShaderStage, source: &CStr) -> Result<ShaderObject, ShaderError> {
+        unsafe {
+            let shader = ShaderObject(gl::CreateShader(stage.gl_enum()));
+            gl::ShaderSource(shader.0, 1, &source.as_ptr(), std::ptr::null::<i32>());
+            gl::CompileShader(shader.0);
+
+            let mut success = gl::FALSE as i32;
+            gl::GetShaderiv(shader.0, gl::COMPILE_STATUS, &mut success);
+            if success != gl::TRUE as GLint {
+                let log = info_log(shader.0, gl::GetShaderiv, gl::GetShaderInfoLog);
+                return Err(ShaderError::Compile { stage, log });
+            }
+
+            Ok(shader)
+        }
+    }
+}
+
+impl Drop for ShaderObject {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteShader(self.0);
+        }
+    }
+}
+
+// Read the whole info log of a shader or program, sized by INFO_LOG_LENGTH
+unsafe fn info_log(
+    object: GLuint,
+    get_iv: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut capacity = 0;
+    get_iv(object, gl::INFO_LOG_LENGTH, &mut capacity);
+
+    let mut info_log = vec![0u8; capacity.max(1) as usize];
+    let mut length = 0;
+    get_log(
+        object,
+        info_log.len() as GLsizei,
+        &mut length,
+        info_log.as_mut_ptr() as *mut GLchar,
+    );
+
+    String::from_utf8_lossy(&info_log[..length as usize])
+        .trim_end()
+        .to_string()
+}
@@ -0,0 +1,161 @@
+use gl::types::*;
+use std::marker::PhantomData;
+
+/// A type with a std140 layout, as used by uniform blocks.
+///
+/// Implemented for `f32`, `i32`, `u32`, `bool` and their 2 to 4 component vectors as arrays,
+/// use `#[derive(Std140)]` for structs.
+pub trait Std140 {
+    const ALIGN: usize;
+    /// Size in bytes, without the padding that arrays add between elements.
+    const SIZE: usize;
+
+    /// Writes the value to the start of `out`, which is at least `SIZE` bytes long.
+    fn write_std140(&self, out: &mut [u8]);
+
+    /// Lists the byte offset of every member under the name a program reports it by.
+    fn std140_members(name: &str, offset: usize, members: &mut Vec<(String, usize)>) {
+        members.push((name.to_string(), offset));
+    }
+}
+
+/// Rounds `offset` up to a multiple of `align`.
+pub const fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
+macro_rules! impl_scalar {
+    ($($type:ty),*) => {$(
+        impl Std140 for $type {
+            const ALIGN: usize = 4;
+            const SIZE: usize = 4;
+
+            fn write_std140(&self, out: &mut [u8]) {
+                out[..4].copy_from_slice(&self.to_ne_bytes());
+            }
+        }
+    )*};
+}
+
+impl_scalar!(f32, i32, u32);
+
+// GLSL bools take four bytes
+impl Std140 for bool {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+
+    fn write_std140(&self, out: &mut [u8]) {
+        (*self as u32).write_std140(out);
+    }
+}
+
+macro_rules! impl_vector {
+    ($($type:ty),*) => {$(
+        // vec3 is aligned like a vec4 but only takes 12 bytes
+        impl Std140 for [$type; 2] {
+            const ALIGN: usize = 8;
+            const SIZE: usize = 8;
+
+            fn write_std140(&self, out: &mut [u8]) {
+                for (index, component) in self.iter().enumerate() {
+                    component.write_std140(&mut out[index * 4..]);
+                }
+            }
+        }
+
+        impl Std140 for [$type; 3] {
+            const ALIGN: usize = 16;
+            const SIZE: usize = 12;
+
+            fn write_std140(&self, out: &mut [u8]) {
+                for (index, component) in self.iter().enumerate() {
+                    component.write_std140(&mut out[index * 4..]);
+                }
+            }
+        }
+
+        impl Std140 for [$type; 4] {
+            const ALIGN: usize = 16;
+            const SIZE: usize = 16;
+
+            fn write_std140(&self, out: &mut [u8]) {
+                for (index, component) in self.iter().enumerate() {
+                    component.write_std140(&mut out[index * 4..]);
+                }
+            }
+        }
+    )*};
+}
+
+impl_vector!(f32, i32, u32);
+
+/// The bytes of `value` as a uniform block expects them.
+pub fn to_bytes<T: Std140>(value: &T) -> Vec<u8> {
+    let mut bytes = vec![0; T::SIZE];
+    value.write_std140(&mut bytes);
+    bytes
+}
+
+/// A buffer holding one `T` for a uniform block, shared by every program it is bound for.
+///
+/// Bind it to a binding point with `bind`, and point each program's block at the same
+/// binding with `Shader::bind_uniform_block`.
+pub struct UniformBuffer<T: Std140> {
+    buffer: GLuint,
+    marker: PhantomData<T>,
+}
+
+impl<T: Std140> UniformBuffer<T> {
+    pub fn new(value: &T) -> UniformBuffer<T> {
+        let bytes = to_bytes(value);
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer);
+            gl::BufferData(
+                gl::UNIFORM_BUFFER,
+                bytes.len() as GLsizeiptr,
+                bytes.as_ptr() as *const _,
+                gl::DYNAMIC_DRAW,
+            );
+        }
+
+        UniformBuffer {
+            buffer,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.buffer
+    }
+
+    /// Replaces the contents, programs using the buffer see the new value on their next draw.
+    pub fn set(&mut self, value: &T) {
+        let bytes = to_bytes(value);
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER,
+                0,
+                bytes.len() as GLsizeiptr,
+                bytes.as_ptr() as *const _,
+            );
+        }
+    }
+
+    /// Binds the buffer to a uniform buffer binding point.
+    pub fn bind(&self, binding: GLuint) {
+        unsafe {
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding, self.buffer);
+        }
+    }
+}
+
+impl<T: Std140> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use crate::error::BlockError;
+use crate::std140::align_to;
+use gl::types::*;
+use std::marker::PhantomData;
+
+/// A type with a std430 layout, as used by shader storage blocks.
+///
+/// Implemented for `f32`, `i32`, `u32`, `bool` and their 2 to 4 component vectors as arrays,
+/// use `#[derive(Std430)]` for structs.
+pub trait Std430: Sized {
+    const ALIGN: usize;
+    /// Size in bytes, already a multiple of `ALIGN` for structs.
+    const SIZE: usize;
+
+    /// Writes the value to the start of `out`, which is at least `SIZE` bytes long.
+    fn write_std430(&self, out: &mut [u8]);
+
+    /// Reads a value back from the start of `bytes`.
+    fn read_std430(bytes: &[u8]) -> Self;
+}
+
+/// The larger of two alignments, usable in constants.
+pub const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+macro_rules! impl_scalar {
+    ($($type:ty),*) => {$(
+        impl Std430 for $type {
+            const ALIGN: usize = 4;
+            const SIZE: usize = 4;
+
+            fn write_std430(&self, out: &mut [u8]) {
+                out[..4].copy_from_slice(&self.to_ne_bytes());
+            }
+
+            fn read_std430(bytes: &[u8]) -> Self {
+                <$type>::from_ne_bytes(bytes[..4].try_into().unwrap())
+            }
+        }
+    )*};
+}
+
+impl_scalar!(f32, i32, u32);
+
+// GLSL bools take four bytes
+impl Std430 for bool {
+    const ALIGN: usize = 4;
+    const SIZE: usize = 4;
+
+    fn write_std430(&self, out: &mut [u8]) {
+        (*self as u32).write_std430(out);
+    }
+
+    fn read_std430(bytes: &[u8]) -> Self {
+        u32::read_std430(bytes) != 0
+    }
+}
+
+macro_rules! impl_vector {
+    ($type:ty, $($length:literal => $align:literal),*) => {$(
+        impl Std430 for [$type; $length] {
+            const ALIGN: usize = $align;
+            const SIZE: usize = $length * 4;
+
+            fn write_std430(&self, out: &mut [u8]) {
+                for (index, component) in self.iter().enumerate() {
+                    component.write_std430(&mut out[index * 4..]);
+                }
+            }
+
+            fn read_std430(bytes: &[u8]) -> Self {
+                std::array::from_fn(|index| <$type>::read_std430(&bytes[index * 4..]))
+            }
+        }
+    )*};
+}
+
+// vec3 is aligned like a vec4 but only takes 12 bytes
+impl_vector!(f32, 2 => 8, 3 => 16, 4 => 16);
+impl_vector!(i32, 2 => 8, 3 => 16, 4 => 16);
+impl_vector!(u32, 2 => 8, 3 => 16, 4 => 16);
+
+// Elements of a runtime sized array such as `Particle particles[];`
+fn stride<T: Std430>() -> usize {
+    align_to(T::SIZE, T::ALIGN)
+}
+
+fn to_bytes<T: Std430>(data: &[T]) -> Vec<u8> {
+    let stride = stride::<T>();
+    let mut bytes = vec![0; stride * data.len()];
+    for (index, value) in data.iter().enumerate() {
+        value.write_std430(&mut bytes[index * stride..]);
+    }
+    bytes
+}
+
+/// A shader storage buffer holding a runtime sized array of `T`, needs OpenGL 4.3.
+///
+/// The buffer matches a block ending in `T name[];`. Bind it with `bind` and point the
+/// program's block at the same binding with `layout(binding = N)` or
+/// `Shader::bind_storage_block`.
+pub struct StorageBuffer<T: Std430> {
+    buffer: GLuint,
+    len: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Std430> StorageBuffer<T> {
+    pub fn new(data: &[T]) -> StorageBuffer<T> {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+        }
+
+        let mut storage = StorageBuffer {
+            buffer,
+            len: 0,
+            marker: PhantomData,
+        };
+        storage.set(data);
+        storage
+    }
+
+    /// A buffer of `len` zeroed elements, for shaders to write into.
+    pub fn zeroed(len: usize) -> StorageBuffer<T> {
+        let mut buffer = 0;
+        let bytes = vec![0u8; stride::<T>() * len];
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+            gl::BufferData(
+                gl::SHADER_STORAGE_BUFFER,
+                bytes.len() as GLsizeiptr,
+                bytes.as_ptr() as *const _,
+                gl::DYNAMIC_COPY,
+            );
+        }
+
+        StorageBuffer {
+            buffer,
+            len,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Replaces the contents, reallocating when the length changes.
+    pub fn set(&mut self, data: &[T]) {
+        let bytes = to_bytes(data);
+        unsafe {
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.buffer);
+            if data.len() == self.len && !bytes.is_empty() {
+                gl::BufferSubData(
+                    gl::SHADER_STORAGE_BUFFER,
+                    0,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_ptr() as *const _,
+                );
+            } else {
+                gl::BufferData(
+                    gl::SHADER_STORAGE_BUFFER,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_ptr() as *const _,
+                    gl::DYNAMIC_COPY,
+                );
+            }
+        }
+        self.len = data.len();
+    }
+
+    /// Copies the contents back to the CPU, stalling until the GPU is done with the buffer.
+    ///
+    /// Shader writes only show up after `memory_barrier(Barrier::BUFFER_UPDATE)`.
+    pub fn read(&self) -> Vec<T> {
+        let stride = stride::<T>();
+        let mut bytes = vec![0u8; stride * self.len];
+        if !bytes.is_empty() {
+            unsafe {
+                gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, self.buffer);
+                gl::GetBufferSubData(
+                    gl::SHADER_STORAGE_BUFFER,
+                    0,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_mut_ptr() as *mut _,
+                );
+            }
+        }
+
+        bytes.chunks_exact(stride).map(T::read_std430).collect()
+    }
+
+    /// Binds the buffer to a shader storage buffer binding point.
+    pub fn bind(&self, binding: GLuint) {
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, binding, self.buffer);
+        }
+    }
+}
+
+impl<T: Std430> Drop for StorageBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
+
+/// A buffer of `atomic_uint` counters, needs OpenGL 4.2.
+///
+/// Counters are bound with `layout(binding = N, offset = 4 * index) uniform atomic_uint`,
+/// there is no API call to change that binding afterwards.
+pub struct AtomicCounterBuffer {
+    buffer: GLuint,
+    len: usize,
+}
+
+impl AtomicCounterBuffer {
+    /// `len` counters starting at zero.
+    pub fn new(len: usize) -> AtomicCounterBuffer {
+        let mut buffer = 0;
+        let values = vec![0u32; len];
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+            gl::BindBuffer(gl::ATOMIC_COUNTER_BUFFER, buffer);
+            gl::BufferData(
+                gl::ATOMIC_COUNTER_BUFFER,
+                (len * 4) as GLsizeiptr,
+                values.as_ptr() as *const _,
+                gl::DYNAMIC_COPY,
+            );
+        }
+
+        AtomicCounterBuffer { buffer, len }
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.buffer
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Overwrites the counters, `values` must hold one value per counter.
+    pub fn set(&mut self, values: &[u32]) -> Result<(), BlockError> {
+        if values.len() != self.len {
+            return Err(BlockError::CounterCount {
+                expected: self.len,
+                found: values.len(),
+            });
+        }
+        self.write(values);
+        Ok(())
+    }
+
+    /// Sets every counter back to zero.
+    pub fn reset(&mut self) {
+        self.write(&vec![0; self.len]);
+    }
+
+    // `values` has one value per counter
+    fn write(&mut self, values: &[u32]) {
+        unsafe {
+            gl::BindBuffer(gl::ATOMIC_COUNTER_BUFFER, self.buffer);
+            gl::BufferSubData(
+                gl::ATOMIC_COUNTER_BUFFER,
+                0,
+                (self.len * 4) as GLsizeiptr,
+                values.as_ptr() as *const _,
+            );
+        }
+    }
+
+    /// Shader increments only show up after `memory_barrier(Barrier::ATOMIC_COUNTER)`.
+    pub fn read(&self) -> Vec<u32> {
+        let mut values = vec![0u32; self.len];
+        unsafe {
+            gl::BindBuffer(gl::ATOMIC_COUNTER_BUFFER, self.buffer);
+            gl::GetBufferSubData(
+                gl::ATOMIC_COUNTER_BUFFER,
+                0,
+                (self.len * 4) as GLsizeiptr,
+                values.as_mut_ptr() as *mut _,
+            );
+        }
+        values
+    }
+
+    pub fn bind(&self, binding: GLuint) {
+        unsafe {
+            gl::BindBufferBase(gl::ATOMIC_COUNTER_BUFFER, binding, self.buffer);
+        }
+    }
+}
+
+impl Drop for AtomicCounterBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
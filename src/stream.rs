@@ -0,0 +1,233 @@
+use crate::buffer::BufferTarget;
+use bytemuck::Pod;
+use gl::types::*;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+// One segment being written by the CPU while the GPU may still read the other two
+const SEGMENTS: usize = 3;
+
+/// A buffer for geometry generated on the CPU every frame, needs OpenGL 3.2.
+///
+/// The storage is split into three segments used in turn. Each frame writes into its own
+/// segment, and `next_frame` fences it so the segment is only written again once the GPU
+/// has finished drawing from it. A frame cannot write more than a segment holds, size the
+/// segments for the busiest frame.
+pub struct StreamBuffer<T: Pod> {
+    buffer: GLuint,
+    target: BufferTarget,
+    ring: Ring,
+    fences: [Option<GLsync>; SEGMENTS],
+    marker: PhantomData<T>,
+}
+
+impl<T: Pod> StreamBuffer<T> {
+    /// A buffer with room for `segment_len` elements per frame.
+    pub fn new(target: BufferTarget, segment_len: usize) -> StreamBuffer<T> {
+        let mut buffer = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut buffer);
+        }
+
+        let stream = StreamBuffer {
+            buffer,
+            target,
+            ring: Ring::new(segment_len),
+            fences: [None; SEGMENTS],
+            marker: PhantomData,
+        };
+        stream.allocate();
+        stream
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.buffer
+    }
+
+    pub fn target(&self) -> BufferTarget {
+        self.target
+    }
+
+    /// Number of elements one frame can write.
+    pub fn segment_len(&self) -> usize {
+        self.ring.segment_len
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBuffer(self.target.gl_enum(), self.buffer);
+        }
+    }
+
+    // Bind without touching the vertex array in use, returning the target bound to
+    fn bind_for_transfer(&self) -> GLenum {
+        let target = self.target.transfer_enum();
+        unsafe {
+            gl::BindBuffer(target, self.buffer);
+        }
+        target
+    }
+
+    /// Appends `data` to this frame's segment and returns where it landed, in elements from
+    /// the start of the buffer, ready to pass as the first vertex or index of a draw.
+    ///
+    /// Returns `None` and writes nothing when `data` does not fit in what is left of the
+    /// segment, or when the driver cannot map the buffer, as after a lost context. The
+    /// ranges written earlier in the frame stay valid.
+    pub fn write(&mut self, data: &[T]) -> Option<Range<usize>> {
+        let range = self.ring.place(data.len())?;
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        if !bytes.is_empty() {
+            let target = self.bind_for_transfer();
+            unsafe {
+                // The fence in `next_frame` already made sure the GPU is done with this range
+                let mapped = gl::MapBufferRange(
+                    target,
+                    (range.start * size_of::<T>()) as GLintptr,
+                    bytes.len() as GLsizeiptr,
+                    gl::MAP_WRITE_BIT | gl::MAP_INVALIDATE_RANGE_BIT | gl::MAP_UNSYNCHRONIZED_BIT,
+                );
+                if mapped.is_null() {
+                    return None;
+                }
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), mapped as *mut u8, bytes.len());
+                gl::UnmapBuffer(target);
+            }
+        }
+
+        self.ring.cursor += data.len();
+        Some(range)
+    }
+
+    /// Number of elements this frame can still write.
+    pub fn remaining(&self) -> usize {
+        self.ring.remaining()
+    }
+
+    /// Call once the frame's draws are issued. Fences the segment just written and moves on
+    /// to the next, waiting only if the GPU is still reading it from three frames ago.
+    pub fn next_frame(&mut self) {
+        unsafe {
+            self.fences[self.ring.segment] = Some(gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0));
+        }
+
+        let segment = self.ring.next_segment();
+        if let Some(fence) = self.fences[segment].take() {
+            wait(fence);
+        }
+    }
+
+    // Allocate the storage, nothing is in flight yet
+    fn allocate(&self) {
+        let target = self.bind_for_transfer();
+        unsafe {
+            gl::BufferData(
+                target,
+                (SEGMENTS * self.ring.segment_len * size_of::<T>()) as GLsizeiptr,
+                std::ptr::null(),
+                gl::STREAM_DRAW,
+            );
+        }
+    }
+}
+
+// Where each frame's writes land, in elements from the start of the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Ring {
+    segment_len: usize,
+    segment: usize,
+    // Elements already written to the current segment
+    cursor: usize,
+}
+
+impl Ring {
+    fn new(segment_len: usize) -> Ring {
+        Ring {
+            segment_len,
+            segment: 0,
+            cursor: 0,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.segment_len - self.cursor
+    }
+
+    // Where `len` more elements would go, `None` if they overflow the segment. The cursor
+    // only moves once they are written
+    fn place(&self, len: usize) -> Option<Range<usize>> {
+        if len > self.remaining() {
+            return None;
+        }
+        let start = self.segment * self.segment_len + self.cursor;
+        Some(start..start + len)
+    }
+
+    // Move on to the next segment, wrapping after the last, and return it
+    fn next_segment(&mut self) -> usize {
+        self.segment = (self.segment + 1) % SEGMENTS;
+        self.cursor = 0;
+        self.segment
+    }
+}
+
+// Block until the commands before `fence` are complete, then delete it
+fn wait(fence: GLsync) {
+    unsafe {
+        // Waits time out after a second, keep waiting until the fence is signaled or fails
+        while gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, 1_000_000_000)
+            == gl::TIMEOUT_EXPIRED
+        {}
+        gl::DeleteSync(fence);
+    }
+}
+
+impl<T: Pod> Drop for StreamBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            for fence in self.fences.iter().flatten() {
+                gl::DeleteSync(*fence);
+            }
+            gl::DeleteBuffers(1, &self.buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn places_writes_after_each_other() {
+        let mut ring = Ring::new(10);
+        assert_eq!(ring.place(4), Some(0..4));
+        ring.cursor += 4;
+        assert_eq!(ring.place(6), Some(4..10));
+        ring.cursor += 6;
+        assert_eq!(ring.remaining(), 0);
+        assert_eq!(ring.place(0), Some(10..10));
+    }
+
+    #[test]
+    fn rejects_writes_that_overflow_the_segment() {
+        let mut ring = Ring::new(10);
+        ring.cursor += 7;
+        assert_eq!(ring.place(4), None);
+        // A rejected write leaves room for one that fits
+        assert_eq!(ring.place(3), Some(7..10));
+        assert_eq!(Ring::new(10).place(11), None);
+    }
+
+    #[test]
+    fn wraps_around_the_segments() {
+        let mut ring = Ring::new(8);
+        ring.cursor += 5;
+        assert_eq!(ring.next_segment(), 1);
+        assert_eq!(ring.remaining(), 8);
+        assert_eq!(ring.place(2), Some(8..10));
+        assert_eq!(ring.next_segment(), 2);
+        assert_eq!(ring.place(8), Some(16..24));
+        assert_eq!(ring.next_segment(), 0);
+        assert_eq!(ring.place(1), Some(0..1));
+    }
+}
@@ -0,0 +1,1189 @@
+use crate::compressed;
+use crate::container::{DataFormat, TextureData};
+use crate::error::TextureError;
+use crate::mipmap::{self, MipmapOptions};
+use crate::sampler::Sampler;
+use crate::uniform::TextureUnit;
+use bytemuck::Pod;
+use gl::types::*;
+use image::{DynamicImage, GenericImageView, Rgba32FImage};
+use std::borrow::Cow;
+use std::f32::consts::PI;
+use std::path::Path;
+
+/// How the colour of a texture is encoded.
+///
+/// # Linear workflow
+///
+/// Lighting, filtering and blending only add up on linear values, while images and
+/// monitors store sRGB. Shaders stay linear with three pieces in place:
+///
+/// - Colour images, such as photos, albedo and UI art, are uploaded as `Srgb` so `texture()`
+///   decodes them to linear values when sampled. Data such as normal maps, roughness, masks
+///   and lookup tables is uploaded as `Linear` and sampled as stored.
+/// - The framebuffer stores sRGB and `context::set_framebuffer_srgb(true)` is on, so the
+///   linear output is encoded on write. The default framebuffer has to be asked for with
+///   `glfw::WindowHint::SRgbCapable(true)`, and `context::default_framebuffer_is_srgb`
+///   tells whether the driver agreed. Linear output written to a plain framebuffer is shown
+///   as if it were sRGB and looks too dark.
+/// - With `GL_FRAMEBUFFER_SRGB` on, blending decodes the destination first, so alpha
+///   blending happens in linear space just like `mix()` in a shader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+impl ColorSpace {
+    /// The colour space an internal format samples in.
+    pub fn of(internal_format: GLenum) -> ColorSpace {
+        match internal_format {
+            gl::SRGB8 | gl::SRGB8_ALPHA8 => ColorSpace::Srgb,
+            format if compressed::is_srgb(format) => ColorSpace::Srgb,
+            _ => ColorSpace::Linear,
+        }
+    }
+}
+
+/// How `Texture2D::from_image` uploads an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureOptions {
+    /// Images store the top row first while OpenGL samples the bottom row at `v = 0`
+    pub flip_vertically: bool,
+    pub mipmaps: bool,
+    /// Build the mipmaps on the CPU with these options instead of `glGenerateMipmap`, whose
+    /// filter is up to the driver
+    pub cpu_mipmaps: Option<MipmapOptions>,
+    /// Which `ColorSpace` the texels are in. Only 8 bit images have sRGB formats, 16 bit and
+    /// float images are taken as linear
+    pub color_space: ColorSpace,
+    /// Store float images, such as `.hdr` and `.exr` files, as 16 bit floats. Half the
+    /// memory of 32 bit floats and plenty of range for colour
+    pub half_float: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> TextureOptions {
+        TextureOptions {
+            flip_vertically: false,
+            mipmaps: true,
+            cpu_mipmaps: None,
+            color_space: ColorSpace::Linear,
+            half_float: false,
+        }
+    }
+}
+
+/// Internal format, pixel format, pixel type and swizzle of an uncompressed image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelFormat {
+    pub internal_format: GLenum,
+    pub format: GLenum,
+    pub pixel_type: GLenum,
+    /// Where the shader's `rgba` come from, so grayscale images do not sample as red
+    pub swizzle: [GLenum; 4],
+}
+
+const IDENTITY: [GLenum; 4] = [gl::RED, gl::GREEN, gl::BLUE, gl::ALPHA];
+const LUMINANCE: [GLenum; 4] = [gl::RED, gl::RED, gl::RED, gl::ONE];
+const LUMINANCE_ALPHA: [GLenum; 4] = [gl::RED, gl::RED, gl::RED, gl::GREEN];
+
+impl PixelFormat {
+    /// A format read as it is, without swizzling.
+    pub fn new(internal_format: GLenum, format: GLenum, pixel_type: GLenum) -> PixelFormat {
+        PixelFormat {
+            internal_format,
+            format,
+            pixel_type,
+            swizzle: IDENTITY,
+        }
+    }
+
+    /// Size of one texel of client data.
+    pub fn bytes_per_pixel(&self) -> usize {
+        // Packed types hold every channel in one 32 bit value
+        if matches!(
+            self.pixel_type,
+            gl::UNSIGNED_INT_10F_11F_11F_REV
+                | gl::UNSIGNED_INT_5_9_9_9_REV
+                | gl::UNSIGNED_INT_2_10_10_10_REV
+        ) {
+            return 4;
+        }
+        let channels = match self.format {
+            gl::RED | gl::RED_INTEGER | gl::DEPTH_COMPONENT => 1,
+            gl::RG | gl::RG_INTEGER => 2,
+            gl::RGB | gl::BGR | gl::RGB_INTEGER => 3,
+            _ => 4,
+        };
+        let size = match self.pixel_type {
+            gl::UNSIGNED_BYTE | gl::BYTE => 1,
+            gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2,
+            _ => 4,
+        };
+        channels * size
+    }
+
+    /// The same format decoded from sRGB when sampled, if it is 8 bit RGB or RGBA.
+    pub fn srgb(self) -> PixelFormat {
+        let internal_format = match self.internal_format {
+            gl::RGB8 => gl::SRGB8,
+            gl::RGBA8 => gl::SRGB8_ALPHA8,
+            other => other,
+        };
+        PixelFormat {
+            internal_format,
+            ..self
+        }
+    }
+
+    /// The same format stored as 16 bit floats if it is a 32 bit float format. The client
+    /// data stays 32 bit, OpenGL converts it on upload.
+    pub fn half_float(self) -> PixelFormat {
+        let internal_format = match self.internal_format {
+            gl::R32F => gl::R16F,
+            gl::RG32F => gl::RG16F,
+            gl::RGB32F => gl::RGB16F,
+            gl::RGBA32F => gl::RGBA16F,
+            other => other,
+        };
+        PixelFormat {
+            internal_format,
+            ..self
+        }
+    }
+
+    /// The format matching the pixels of `image`, or `None` for types OpenGL cannot take
+    /// as they are.
+    pub fn of(image: &DynamicImage) -> Option<PixelFormat> {
+        let (internal_format, format, pixel_type, swizzle) = match image {
+            DynamicImage::ImageLuma8(_) => (gl::R8, gl::RED, gl::UNSIGNED_BYTE, LUMINANCE),
+            DynamicImage::ImageLumaA8(_) => (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, LUMINANCE_ALPHA),
+            DynamicImage::ImageRgb8(_) => (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, IDENTITY),
+            DynamicImage::ImageRgba8(_) => (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, IDENTITY),
+            DynamicImage::ImageLuma16(_) => (gl::R16, gl::RED, gl::UNSIGNED_SHORT, LUMINANCE),
+            DynamicImage::ImageLumaA16(_) => {
+                (gl::RG16, gl::RG, gl::UNSIGNED_SHORT, LUMINANCE_ALPHA)
+            }
+            DynamicImage::ImageRgb16(_) => (gl::RGB16, gl::RGB, gl::UNSIGNED_SHORT, IDENTITY),
+            DynamicImage::ImageRgba16(_) => (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, IDENTITY),
+            DynamicImage::ImageRgb32F(_) => (gl::RGB32F, gl::RGB, gl::FLOAT, IDENTITY),
+            DynamicImage::ImageRgba32F(_) => (gl::RGBA32F, gl::RGBA, gl::FLOAT, IDENTITY),
+            _ => return None,
+        };
+
+        Some(PixelFormat {
+            internal_format,
+            format,
+            pixel_type,
+            swizzle,
+        })
+    }
+}
+
+/// The largest `GL_UNPACK_ALIGNMENT` rows of `row_bytes` bytes satisfy. The default of 4
+/// breaks RGB8 and single channel images whose width is not a multiple of 4.
+pub(crate) fn unpack_alignment(row_bytes: usize) -> GLint {
+    1 << row_bytes.trailing_zeros().min(3)
+}
+
+// Flip the image if asked to, convert types OpenGL cannot take as they are to RGBA8 and
+// pick the internal format
+fn prepare<'a>(
+    image: &'a DynamicImage,
+    options: &TextureOptions,
+) -> (Cow<'a, DynamicImage>, PixelFormat) {
+    let mut image = Cow::Borrowed(image);
+    if options.flip_vertically {
+        image = Cow::Owned(image.flipv());
+    }
+    // There is no core sRGB format with fewer than three channels
+    if options.color_space == ColorSpace::Srgb {
+        match *image {
+            DynamicImage::ImageLuma8(_) => image = Cow::Owned(image.to_rgb8().into()),
+            DynamicImage::ImageLumaA8(_) => image = Cow::Owned(image.to_rgba8().into()),
+            _ => {}
+        }
+    }
+    let (image, mut format) = match PixelFormat::of(&image) {
+        Some(format) => (image, format),
+        None => {
+            let image = DynamicImage::ImageRgba8(image.to_rgba8());
+            let format = PixelFormat::of(&image).unwrap();
+            (Cow::Owned(image), format)
+        }
+    };
+    if options.color_space == ColorSpace::Srgb {
+        format = format.srgb();
+    }
+    if options.half_float {
+        format = format.half_float();
+    }
+    (image, format)
+}
+
+/// The CPU side of `Texture2D::from_image`, done ahead so only the upload is left for the
+/// GL thread.
+pub(crate) struct PreparedImage {
+    image: DynamicImage,
+    format: PixelFormat,
+    chain: Vec<DynamicImage>,
+    options: TextureOptions,
+}
+
+impl PreparedImage {
+    pub(crate) fn new(image: DynamicImage, options: TextureOptions) -> PreparedImage {
+        let (prepared, format) = prepare(&image, &options);
+        let image = match prepared {
+            Cow::Borrowed(_) => image,
+            Cow::Owned(prepared) => prepared,
+        };
+        let chain = cpu_mip_chain(&image, &format, &options);
+
+        PreparedImage {
+            image,
+            format,
+            chain,
+            options,
+        }
+    }
+}
+
+// Prepare images that become the faces or layers of one texture, which have to agree on
+// size and format
+fn prepare_layers<'a>(
+    images: &'a [DynamicImage],
+    options: &TextureOptions,
+) -> Result<(Vec<Cow<'a, DynamicImage>>, PixelFormat), TextureError> {
+    let mut layers = Vec::with_capacity(images.len());
+    let mut first: Option<((u32, u32), PixelFormat)> = None;
+    for image in images {
+        let (layer, format) = prepare(image, options);
+        let size = layer.dimensions();
+        match first {
+            None => first = Some((size, format)),
+            Some((expected, _)) if expected != size => {
+                return Err(TextureError::SizeMismatch {
+                    expected,
+                    found: size,
+                })
+            }
+            Some((_, expected)) if expected.internal_format != format.internal_format => {
+                return Err(TextureError::FormatMismatch {
+                    expected: expected.internal_format,
+                    found: format.internal_format,
+                })
+            }
+            Some(_) => {}
+        }
+        layers.push(layer);
+    }
+
+    match first {
+        Some((_, format)) => Ok((layers, format)),
+        None => Err(TextureError::Empty),
+    }
+}
+
+// Where the mip levels of a new texture come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mipmaps {
+    None,
+    Generate,
+    /// This many levels, the base level included, were uploaded
+    Uploaded(usize),
+}
+
+impl Mipmaps {
+    // Generated unless turned off or `chain_len` levels below the base were built on the CPU
+    fn from_options(options: &TextureOptions, chain_len: usize) -> Mipmaps {
+        match (options.mipmaps, chain_len) {
+            (false, _) => Mipmaps::None,
+            (true, 0) => Mipmaps::Generate,
+            (true, chain_len) => Mipmaps::Uploaded(chain_len + 1),
+        }
+    }
+}
+
+// Swizzle, complete the mipmaps and unbind once the texture bound to `target` holds its data
+unsafe fn finish(target: GLenum, swizzle: [GLenum; 4], mipmaps: Mipmaps) {
+    let swizzle = swizzle.map(|channel| channel as GLint);
+    gl::TexParameteriv(target, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+
+    match mipmaps {
+        Mipmaps::Generate => gl::GenerateMipmap(target),
+        Mipmaps::Uploaded(levels) if levels > 1 => {
+            // Only the levels uploaded, the texture is complete without the rest
+            gl::TexParameteri(target, gl::TEXTURE_MAX_LEVEL, levels as GLint - 1);
+        }
+        _ => {
+            // The default minification filter reads mipmaps, without them the texture would
+            // be incomplete and sample as black
+            gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+        }
+    }
+
+    gl::BindTexture(target, 0);
+}
+
+// The levels below `image` built on the CPU if `options` ask for them, none otherwise
+fn cpu_mip_chain(
+    image: &DynamicImage,
+    format: &PixelFormat,
+    options: &TextureOptions,
+) -> Vec<DynamicImage> {
+    match options.cpu_mipmaps {
+        Some(mipmap_options) if options.mipmaps => {
+            let srgb = ColorSpace::of(format.internal_format) == ColorSpace::Srgb;
+            mipmap::mip_chain(image, &mipmap_options, srgb)
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Specify mip `level` of the 2D texture or cubemap face `target` from `image`
+unsafe fn tex_image_2d(target: GLenum, level: usize, image: &DynamicImage, format: &PixelFormat) {
+    let (width, height) = image.dimensions();
+    gl::PixelStorei(
+        gl::UNPACK_ALIGNMENT,
+        unpack_alignment(width as usize * format.bytes_per_pixel()),
+    );
+    let size = (width, height, 1);
+    tex_image(
+        target,
+        level,
+        size,
+        &DataFormat::Uncompressed(*format),
+        image.as_bytes(),
+    );
+}
+
+// Create a texture and bind it to `target`, ready for its data
+unsafe fn create(target: GLenum) -> GLuint {
+    let mut texture = 0;
+    gl::GenTextures(1, &mut texture);
+    gl::BindTexture(target, texture);
+    texture
+}
+
+// Mip levels, each holding every image of that level
+type Levels<'a> = Cow<'a, [Vec<u8>]>;
+
+// The levels of `data` as they will be uploaded, decompressed on the CPU when the driver
+// lacks their compressed format
+fn resolve(data: &TextureData) -> Result<(DataFormat, Levels<'_>), TextureError> {
+    let (format, srgb) = match data.format {
+        DataFormat::Compressed { format, srgb } => (format, srgb),
+        DataFormat::Uncompressed(_) => return Ok((data.format, Cow::Borrowed(&data.levels))),
+    };
+    if format.gl_internal_format(srgb).is_some() && format.is_supported() {
+        return Ok((data.format, Cow::Borrowed(&data.levels)));
+    }
+
+    let unsupported =
+        || TextureError::UnsupportedFormat(format!("{format:?} without driver support"));
+    let mut decoded_format = None;
+    let mut levels = Vec::with_capacity(data.levels.len());
+    for (index, level) in data.levels.iter().enumerate() {
+        let (width, height, depth) = data.level_size(index);
+        let images = (depth * data.layers * data.faces) as usize;
+        let mut decoded = Vec::new();
+        for image in level
+            .chunks_exact(format.image_size(width, height))
+            .take(images)
+        {
+            let (pixel_format, pixels) = format
+                .decompress(srgb, width, height, image)
+                .ok_or_else(unsupported)?;
+            decoded_format = Some(pixel_format);
+            decoded.extend_from_slice(&pixels);
+        }
+        levels.push(decoded);
+    }
+
+    let format = decoded_format.ok_or_else(unsupported)?;
+    Ok((DataFormat::Uncompressed(format), Cow::Owned(levels)))
+}
+
+// Create a texture bound to `target` holding every level of `data`, and return it with its
+// internal format
+fn create_from_data(target: GLenum, data: &TextureData) -> Result<(GLuint, GLenum), TextureError> {
+    data.check_sizes()?;
+    let (format, levels) = resolve(data)?;
+    let (internal_format, swizzle) = match format {
+        DataFormat::Uncompressed(format) => (format.internal_format, format.swizzle),
+        DataFormat::Compressed { format, srgb } => {
+            (format.gl_internal_format(srgb).unwrap(), IDENTITY)
+        }
+    };
+
+    let texture;
+    unsafe {
+        texture = create(target);
+        for (index, level) in levels.iter().enumerate() {
+            let (width, height, depth) = data.level_size(index);
+            if let DataFormat::Uncompressed(format) = format {
+                gl::PixelStorei(
+                    gl::UNPACK_ALIGNMENT,
+                    unpack_alignment(width as usize * format.bytes_per_pixel()),
+                );
+            }
+            match target {
+                gl::TEXTURE_CUBE_MAP => {
+                    let face_size = format.image_size(width, height);
+                    for (face, bytes) in level.chunks_exact(face_size).take(6).enumerate() {
+                        let face_target = gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum;
+                        tex_image(face_target, index, (width, height, 1), &format, bytes);
+                    }
+                }
+                gl::TEXTURE_2D_ARRAY => {
+                    let size = (width, height, data.layers);
+                    tex_image(target, index, size, &format, level);
+                }
+                _ => tex_image(target, index, (width, height, depth), &format, level),
+            }
+        }
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        finish(target, swizzle, Mipmaps::Uploaded(levels.len()));
+    }
+
+    Ok((texture, internal_format))
+}
+
+// Specify one level of the texture bound to `target`, the depth is only read for array and
+// 3D textures
+unsafe fn tex_image(
+    target: GLenum,
+    level: usize,
+    (width, height, depth): (u32, u32, u32),
+    format: &DataFormat,
+    bytes: &[u8],
+) {
+    let three_dimensional = matches!(target, gl::TEXTURE_2D_ARRAY | gl::TEXTURE_3D);
+    let (level, width, height, depth) = (
+        level as GLint,
+        width as GLsizei,
+        height as GLsizei,
+        depth as GLsizei,
+    );
+    let pixels = bytes.as_ptr() as *const _;
+    match *format {
+        DataFormat::Uncompressed(format) if three_dimensional => gl::TexImage3D(
+            target,
+            level,
+            format.internal_format as GLint,
+            width,
+            height,
+            depth,
+            0,
+            format.format,
+            format.pixel_type,
+            pixels,
+        ),
+        DataFormat::Uncompressed(format) => gl::TexImage2D(
+            target,
+            level,
+            format.internal_format as GLint,
+            width,
+            height,
+            0,
+            format.format,
+            format.pixel_type,
+            pixels,
+        ),
+        DataFormat::Compressed { format, srgb } => {
+            let internal_format = format.gl_internal_format(srgb).unwrap();
+            let size = bytes.len() as GLsizei;
+            if three_dimensional {
+                gl::CompressedTexImage3D(
+                    target,
+                    level,
+                    internal_format,
+                    width,
+                    height,
+                    depth,
+                    0,
+                    size,
+                    pixels,
+                );
+            } else {
+                gl::CompressedTexImage2D(
+                    target,
+                    level,
+                    internal_format,
+                    width,
+                    height,
+                    0,
+                    size,
+                    pixels,
+                );
+            }
+        }
+    }
+}
+
+// Texture data has to have the layers, faces and depth of the texture it goes into
+fn check_shape(data: &TextureData, expected: &'static str, fits: bool) -> Result<(), TextureError> {
+    match fits {
+        true => Ok(()),
+        false => Err(TextureError::Shape {
+            expected,
+            layers: data.layers,
+            faces: data.faces,
+            depth: data.depth,
+        }),
+    }
+}
+
+macro_rules! impl_texture {
+    ($type:ident, $target:expr) => {
+        impl $type {
+            pub fn get(&self) -> GLuint {
+                self.texture
+            }
+
+            pub fn internal_format(&self) -> GLenum {
+                self.internal_format
+            }
+
+            pub fn color_space(&self) -> ColorSpace {
+                ColorSpace::of(self.internal_format)
+            }
+
+            /// Makes `unit` the active texture unit and binds the texture to it.
+            pub fn bind(&self, unit: TextureUnit) {
+                unsafe {
+                    gl::ActiveTexture(gl::TEXTURE0 + unit.0);
+                    gl::BindTexture($target, self.texture);
+                }
+            }
+
+            /// Binds the texture and `sampler` to `unit` in one go, the sampler's parameters
+            /// replacing the texture's own.
+            pub fn bind_with_sampler(&self, unit: TextureUnit, sampler: &Sampler) {
+                self.bind(unit);
+                sampler.bind(unit);
+            }
+        }
+
+        impl Drop for $type {
+            fn drop(&mut self) {
+                unsafe {
+                    gl::DeleteTextures(1, &self.texture);
+                }
+            }
+        }
+    };
+}
+
+/// Owns a 2D texture, which is deleted when dropped.
+pub struct Texture2D {
+    texture: GLuint,
+    width: u32,
+    height: u32,
+    internal_format: GLenum,
+}
+
+impl_texture!(Texture2D, gl::TEXTURE_2D);
+
+impl Texture2D {
+    /// Uploads `image` with the internal format matching its pixels, converting types
+    /// OpenGL cannot take as they are to RGBA8. 16 bit and float images keep their
+    /// precision.
+    pub fn from_image(image: &DynamicImage, options: TextureOptions) -> Texture2D {
+        let (image, format) = prepare(image, &options);
+        let chain = cpu_mip_chain(&image, &format, &options);
+        Texture2D::upload(&image, &format, &chain, &options)
+    }
+
+    /// Uploads an image a worker thread already flipped, converted and built mips for.
+    pub(crate) fn from_prepared(prepared: &PreparedImage) -> Texture2D {
+        Texture2D::upload(
+            &prepared.image,
+            &prepared.format,
+            &prepared.chain,
+            &prepared.options,
+        )
+    }
+
+    fn upload(
+        image: &DynamicImage,
+        format: &PixelFormat,
+        chain: &[DynamicImage],
+        options: &TextureOptions,
+    ) -> Texture2D {
+        let (width, height) = image.dimensions();
+
+        let texture;
+        unsafe {
+            texture = create(gl::TEXTURE_2D);
+            tex_image_2d(gl::TEXTURE_2D, 0, image, format);
+            for (index, level) in chain.iter().enumerate() {
+                tex_image_2d(gl::TEXTURE_2D, index + 1, level, format);
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            let mipmaps = Mipmaps::from_options(options, chain.len());
+            finish(gl::TEXTURE_2D, format.swizzle, mipmaps);
+        }
+
+        Texture2D {
+            texture,
+            width,
+            height,
+            internal_format: format.internal_format,
+        }
+    }
+
+    /// Uploads a texture loaded from a KTX2 or DDS file with its mip chain.
+    pub fn from_data(data: &TextureData) -> Result<Texture2D, TextureError> {
+        let fits = data.layers == 1 && data.faces == 1 && data.depth == 1;
+        check_shape(data, "2D texture", fits)?;
+        let (texture, internal_format) = create_from_data(gl::TEXTURE_2D, data)?;
+
+        Ok(Texture2D {
+            texture,
+            width: data.width,
+            height: data.height,
+            internal_format,
+        })
+    }
+
+    /// Loads an image file and uploads it with `from_image`. Radiance `.hdr` and OpenEXR
+    /// `.exr` files load as 32 bit floats, or 16 bit with `TextureOptions::half_float`.
+    pub fn from_file(path: &Path, options: TextureOptions) -> image::ImageResult<Texture2D> {
+        let image = image::open(path)?;
+        Ok(Texture2D::from_image(&image, options))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Owns a cubemap texture, which is deleted when dropped.
+///
+/// Sample it with a `samplerCube` and a direction, as for a skybox. Filtering across face
+/// edges needs `gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS)`.
+pub struct TextureCube {
+    texture: GLuint,
+    size: u32,
+    internal_format: GLenum,
+}
+
+impl_texture!(TextureCube, gl::TEXTURE_CUBE_MAP);
+
+impl TextureCube {
+    /// Uploads six square faces in the order +X, -X, +Y, -Y, +Z, -Z.
+    ///
+    /// Faces are laid out with their top row first, as image files already are, so they
+    /// usually should not be flipped.
+    pub fn from_faces(
+        faces: &[DynamicImage; 6],
+        options: TextureOptions,
+    ) -> Result<TextureCube, TextureError> {
+        let (faces, format) = prepare_layers(faces, &options)?;
+        let (width, height) = faces[0].dimensions();
+        if width != height {
+            return Err(TextureError::NotSquare { width, height });
+        }
+
+        let texture;
+        unsafe {
+            texture = create(gl::TEXTURE_CUBE_MAP);
+            let mut chain_len = 0;
+            for (index, face) in faces.iter().enumerate() {
+                let face_target = gl::TEXTURE_CUBE_MAP_POSITIVE_X + index as GLenum;
+                tex_image_2d(face_target, 0, face, &format);
+                let chain = cpu_mip_chain(face, &format, &options);
+                for (index, level) in chain.iter().enumerate() {
+                    tex_image_2d(face_target, index + 1, level, &format);
+                }
+                chain_len = chain.len();
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            let mipmaps = Mipmaps::from_options(&options, chain_len);
+            finish(gl::TEXTURE_CUBE_MAP, format.swizzle, mipmaps);
+        }
+
+        Ok(TextureCube {
+            texture,
+            size: width,
+            internal_format: format.internal_format,
+        })
+    }
+
+    /// Uploads a cubemap loaded from a KTX2 or DDS file with its mip chain.
+    pub fn from_data(data: &TextureData) -> Result<TextureCube, TextureError> {
+        let fits = data.layers == 1 && data.faces == 6 && data.depth == 1;
+        check_shape(data, "cubemap", fits)?;
+        if data.width != data.height {
+            return Err(TextureError::NotSquare {
+                width: data.width,
+                height: data.height,
+            });
+        }
+        let (texture, internal_format) = create_from_data(gl::TEXTURE_CUBE_MAP, data)?;
+
+        Ok(TextureCube {
+            texture,
+            size: data.width,
+            internal_format,
+        })
+    }
+
+    /// Projects an equirectangular panorama, longitude across and latitude down, onto six
+    /// faces of `size` pixels. Float panoramas stay float.
+    pub fn from_equirectangular(
+        panorama: &DynamicImage,
+        size: u32,
+        options: TextureOptions,
+    ) -> Result<TextureCube, TextureError> {
+        let (width, height) = panorama.dimensions();
+        if width == 0 || height == 0 {
+            return Err(TextureError::ZeroSize { width, height });
+        }
+        if size == 0 {
+            return Err(TextureError::ZeroSize {
+                width: size,
+                height: size,
+            });
+        }
+
+        let (panorama, format) = prepare(panorama, &options);
+        let source = panorama.to_rgba32f();
+        let faces = std::array::from_fn(|face| {
+            let face = DynamicImage::ImageRgba32F(equirectangular_face(&source, face, size));
+            match format.pixel_type {
+                gl::FLOAT => face,
+                gl::UNSIGNED_SHORT => DynamicImage::ImageRgba16(face.to_rgba16()),
+                _ => DynamicImage::ImageRgba8(face.to_rgba8()),
+            }
+        });
+
+        let options = TextureOptions {
+            flip_vertically: false,
+            ..options
+        };
+        TextureCube::from_faces(&faces, options)
+    }
+
+    /// Width and height of each face.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+}
+
+// Render one cubemap face by looking up the panorama in the direction of each texel
+fn equirectangular_face(source: &Rgba32FImage, face: usize, size: u32) -> Rgba32FImage {
+    Rgba32FImage::from_fn(size, size, |x, y| {
+        // Face coordinates from -1 to 1, with the first row at t = -1 as OpenGL reads it
+        let s = 2.0 * (x as f32 + 0.5) / size as f32 - 1.0;
+        let t = 2.0 * (y as f32 + 0.5) / size as f32 - 1.0;
+        let [x, y, z] = match face {
+            0 => [1.0, -t, -s],
+            1 => [-1.0, -t, s],
+            2 => [s, 1.0, t],
+            3 => [s, -1.0, -t],
+            4 => [s, -t, 1.0],
+            _ => [-s, -t, -1.0],
+        };
+
+        let longitude = x.atan2(-z);
+        let latitude = (y / (x * x + y * y + z * z).sqrt()).asin();
+        let u = 0.5 + longitude / (2.0 * PI);
+        let v = 0.5 - latitude / PI;
+        image::Rgba(sample_bilinear(source, u, v))
+    })
+}
+
+// Wraps around horizontally and clamps at the poles
+fn sample_bilinear(source: &Rgba32FImage, u: f32, v: f32) -> [f32; 4] {
+    let (width, height) = source.dimensions();
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+
+    let texel = |x: f32, y: f32| {
+        let x = (x as i64).rem_euclid(width as i64) as u32;
+        let y = (y as u32).min(height - 1);
+        source.get_pixel(x, y).0
+    };
+    let (a, b) = (texel(x0, y0), texel(x0 + 1.0, y0));
+    let (c, d) = (texel(x0, y0 + 1.0), texel(x0 + 1.0, y0 + 1.0));
+    std::array::from_fn(|channel| {
+        let top = a[channel] + (b[channel] - a[channel]) * fx;
+        let bottom = c[channel] + (d[channel] - c[channel]) * fx;
+        top + (bottom - top) * fy
+    })
+}
+
+/// Owns a 2D array texture, which is deleted when dropped.
+///
+/// Sample it with a `sampler2DArray` and the layer index as the third coordinate. Unlike an
+/// atlas, layers never bleed into each other when filtered or mipmapped.
+pub struct Texture2DArray {
+    texture: GLuint,
+    width: u32,
+    height: u32,
+    layers: u32,
+    internal_format: GLenum,
+}
+
+impl_texture!(Texture2DArray, gl::TEXTURE_2D_ARRAY);
+
+impl Texture2DArray {
+    /// Uploads each image as a layer, they all have to be the same size and type.
+    pub fn from_images(
+        images: &[DynamicImage],
+        options: TextureOptions,
+    ) -> Result<Texture2DArray, TextureError> {
+        let (layers, format) = prepare_layers(images, &options)?;
+        let (width, height) = layers[0].dimensions();
+
+        let texture;
+        unsafe {
+            texture = create(gl::TEXTURE_2D_ARRAY);
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                format.internal_format as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                layers.len() as GLsizei,
+                0,
+                format.format,
+                format.pixel_type,
+                std::ptr::null(),
+            );
+            gl::PixelStorei(
+                gl::UNPACK_ALIGNMENT,
+                unpack_alignment(width as usize * format.bytes_per_pixel()),
+            );
+            for (index, layer) in layers.iter().enumerate() {
+                gl::TexSubImage3D(
+                    gl::TEXTURE_2D_ARRAY,
+                    0,
+                    0,
+                    0,
+                    index as GLint,
+                    width as GLsizei,
+                    height as GLsizei,
+                    1,
+                    format.format,
+                    format.pixel_type,
+                    layer.as_bytes().as_ptr() as *const _,
+                );
+            }
+
+            // Each level takes every layer at once
+            let chains: Vec<Vec<DynamicImage>> = layers
+                .iter()
+                .map(|layer| cpu_mip_chain(layer, &format, &options))
+                .collect();
+            for level in 0..chains[0].len() {
+                let (level_width, level_height) = chains[0][level].dimensions();
+                let bytes: Vec<u8> = chains
+                    .iter()
+                    .flat_map(|chain| chain[level].as_bytes())
+                    .copied()
+                    .collect();
+                gl::PixelStorei(
+                    gl::UNPACK_ALIGNMENT,
+                    unpack_alignment(level_width as usize * format.bytes_per_pixel()),
+                );
+                let size = (level_width, level_height, layers.len() as u32);
+                let data_format = DataFormat::Uncompressed(format);
+                tex_image(gl::TEXTURE_2D_ARRAY, level + 1, size, &data_format, &bytes);
+            }
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            let mipmaps = Mipmaps::from_options(&options, chains[0].len());
+            finish(gl::TEXTURE_2D_ARRAY, format.swizzle, mipmaps);
+        }
+
+        Ok(Texture2DArray {
+            texture,
+            width,
+            height,
+            layers: layers.len() as u32,
+            internal_format: format.internal_format,
+        })
+    }
+
+    /// Uploads an array texture loaded from a KTX2 or DDS file with its mip chain, a file
+    /// without layers becomes a single layer.
+    pub fn from_data(data: &TextureData) -> Result<Texture2DArray, TextureError> {
+        check_shape(data, "2D array texture", data.faces == 1 && data.depth == 1)?;
+        let (texture, internal_format) = create_from_data(gl::TEXTURE_2D_ARRAY, data)?;
+
+        Ok(Texture2DArray {
+            texture,
+            width: data.width,
+            height: data.height,
+            layers: data.layers,
+            internal_format,
+        })
+    }
+
+    /// Splits a sprite sheet into `columns` by `rows` equal cells, one layer each, counting
+    /// along the rows from the top left.
+    pub fn from_grid(
+        sheet: &DynamicImage,
+        columns: u32,
+        rows: u32,
+        options: TextureOptions,
+    ) -> Result<Texture2DArray, TextureError> {
+        let (width, height) = sheet.dimensions();
+        if columns == 0 || rows == 0 || width % columns != 0 || height % rows != 0 {
+            return Err(TextureError::InvalidGrid {
+                width,
+                height,
+                columns,
+                rows,
+            });
+        }
+
+        let (cell_width, cell_height) = (width / columns, height / rows);
+        let cells: Vec<DynamicImage> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                sheet.crop_imm(
+                    column * cell_width,
+                    row * cell_height,
+                    cell_width,
+                    cell_height,
+                )
+            })
+            .collect();
+        Texture2DArray::from_images(&cells, options)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+}
+
+/// Owns a 3D texture, which is deleted when dropped.
+pub struct Texture3D {
+    texture: GLuint,
+    width: u32,
+    height: u32,
+    depth: u32,
+    internal_format: GLenum,
+}
+
+impl_texture!(Texture3D, gl::TEXTURE_3D);
+
+impl Texture3D {
+    /// Uploads a volume stored slice by slice, each slice row by row, in `format`.
+    pub fn from_raw<T: Pod>(
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: PixelFormat,
+        data: &[T],
+        mipmaps: bool,
+    ) -> Result<Texture3D, TextureError> {
+        let bytes: &[u8] = bytemuck::cast_slice(data);
+        let expected = width as usize * height as usize * depth as usize * format.bytes_per_pixel();
+        if bytes.len() != expected {
+            return Err(TextureError::DataSize {
+                expected,
+                found: bytes.len(),
+            });
+        }
+
+        let texture;
+        unsafe {
+            texture = create(gl::TEXTURE_3D);
+            gl::PixelStorei(
+                gl::UNPACK_ALIGNMENT,
+                unpack_alignment(width as usize * format.bytes_per_pixel()),
+            );
+            gl::TexImage3D(
+                gl::TEXTURE_3D,
+                0,
+                format.internal_format as GLint,
+                width as GLsizei,
+                height as GLsizei,
+                depth as GLsizei,
+                0,
+                format.format,
+                format.pixel_type,
+                bytes.as_ptr() as *const _,
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            let mipmaps = match mipmaps {
+                true => Mipmaps::Generate,
+                false => Mipmaps::None,
+            };
+            finish(gl::TEXTURE_3D, format.swizzle, mipmaps);
+        }
+
+        Ok(Texture3D {
+            texture,
+            width,
+            height,
+            depth,
+            internal_format: format.internal_format,
+        })
+    }
+
+    /// Uploads a volume loaded from a KTX2 or DDS file with its mip chain.
+    pub fn from_data(data: &TextureData) -> Result<Texture3D, TextureError> {
+        check_shape(data, "3D texture", data.layers == 1 && data.faces == 1)?;
+        let (texture, internal_format) = create_from_data(gl::TEXTURE_3D, data)?;
+
+        Ok(Texture3D {
+            texture,
+            width: data.width,
+            height: data.height,
+            depth: data.depth,
+            internal_format,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_follow_the_image_type() {
+        let cases = [
+            (
+                DynamicImage::new_luma8(1, 1),
+                (gl::R8, gl::RED, gl::UNSIGNED_BYTE, LUMINANCE),
+            ),
+            (
+                DynamicImage::new_luma_a8(1, 1),
+                (gl::RG8, gl::RG, gl::UNSIGNED_BYTE, LUMINANCE_ALPHA),
+            ),
+            (
+                DynamicImage::new_rgb8(1, 1),
+                (gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE, IDENTITY),
+            ),
+            (
+                DynamicImage::new_rgba8(1, 1),
+                (gl::RGBA8, gl::RGBA, gl::UNSIGNED_BYTE, IDENTITY),
+            ),
+            (
+                DynamicImage::new_luma16(1, 1),
+                (gl::R16, gl::RED, gl::UNSIGNED_SHORT, LUMINANCE),
+            ),
+            (
+                DynamicImage::new_rgba16(1, 1),
+                (gl::RGBA16, gl::RGBA, gl::UNSIGNED_SHORT, IDENTITY),
+            ),
+            (
+                DynamicImage::new_rgb32f(1, 1),
+                (gl::RGB32F, gl::RGB, gl::FLOAT, IDENTITY),
+            ),
+            (
+                DynamicImage::new_rgba32f(1, 1),
+                (gl::RGBA32F, gl::RGBA, gl::FLOAT, IDENTITY),
+            ),
+        ];
+        for (image, (internal_format, format, pixel_type, swizzle)) in cases {
+            let expected = PixelFormat {
+                internal_format,
+                format,
+                pixel_type,
+                swizzle,
+            };
+            assert_eq!(
+                PixelFormat::of(&image),
+                Some(expected),
+                "{:?}",
+                image.color()
+            );
+        }
+    }
+
+    #[test]
+    fn half_float_narrows_only_float_formats() {
+        let cases = [
+            (gl::R32F, gl::R16F),
+            (gl::RG32F, gl::RG16F),
+            (gl::RGB32F, gl::RGB16F),
+            (gl::RGBA32F, gl::RGBA16F),
+            (gl::RGBA8, gl::RGBA8),
+            (gl::RGB16, gl::RGB16),
+        ];
+        for (from, to) in cases {
+            let format = PixelFormat::new(from, gl::RGBA, gl::FLOAT);
+            let half = format.half_float();
+            assert_eq!(half.internal_format, to);
+            // The client data stays as it is, OpenGL converts it on upload
+            assert_eq!(half.format, format.format);
+            assert_eq!(half.pixel_type, format.pixel_type);
+            assert_eq!(half.bytes_per_pixel(), format.bytes_per_pixel());
+        }
+    }
+
+    #[test]
+    fn half_float_option_applies_to_float_images() {
+        let options = TextureOptions {
+            half_float: true,
+            ..TextureOptions::default()
+        };
+        let hdr = DynamicImage::new_rgb32f(2, 2);
+        let (image, format) = prepare(&hdr, &options);
+        assert_eq!(format.internal_format, gl::RGB16F);
+        assert_eq!(format.pixel_type, gl::FLOAT);
+        assert_eq!(image.as_bytes().len(), 2 * 2 * 12);
+
+        let ldr = DynamicImage::new_rgba8(2, 2);
+        let (_, format) = prepare(&ldr, &options);
+        assert_eq!(format.internal_format, gl::RGBA8);
+
+        let (_, format) = prepare(&hdr, &TextureOptions::default());
+        assert_eq!(format.internal_format, gl::RGB32F);
+    }
+
+    // Alignment of the rows of a real image, as the upload computes it
+    fn row_alignment(image: &DynamicImage) -> GLint {
+        unpack_alignment(image.as_bytes().len() / image.height() as usize)
+    }
+
+    #[test]
+    fn unpack_alignment_fits_the_rows() {
+        // Odd width RGB8 rows are 3 bytes a texel and only byte aligned
+        assert_eq!(row_alignment(&DynamicImage::new_rgb8(5, 3)), 1);
+        assert_eq!(row_alignment(&DynamicImage::new_rgb8(1, 1)), 1);
+        assert_eq!(row_alignment(&DynamicImage::new_rgb8(2, 2)), 2);
+        assert_eq!(row_alignment(&DynamicImage::new_rgb8(4, 2)), 4);
+        assert_eq!(row_alignment(&DynamicImage::new_luma8(6, 2)), 2);
+        assert_eq!(row_alignment(&DynamicImage::new_luma_a8(3, 2)), 2);
+        assert_eq!(row_alignment(&DynamicImage::new_rgba8(3, 2)), 4);
+        assert_eq!(row_alignment(&DynamicImage::new_rgba8(2, 2)), 8);
+        assert_eq!(row_alignment(&DynamicImage::new_rgb32f(3, 1)), 4);
+        // Never above the largest alignment OpenGL takes
+        assert_eq!(unpack_alignment(64), 8);
+        assert_eq!(unpack_alignment(0), 8);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::introspection;
+use gl::types::*;
+use std::collections::HashMap;
+
+/// A texture unit index, for setting `sampler*` uniforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureUnit(pub u32);
+
+/// A value that can be written to a uniform of a matching GLSL type.
+pub trait Uniform {
+    /// Whether a uniform of `gl_type` (as reported by `glGetActiveUniform`) can hold this value
+    fn accepts(gl_type: GLenum) -> bool;
+
+    /// # Safety
+    /// The program owning `location` must be in use.
+    unsafe fn set(&self, location: GLint);
+}
+
+// Location and type of an active uniform, looked up once per program
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UniformSlot {
+    pub location: GLint,
+    pub gl_type: GLenum,
+}
+
+// Location and type of every active uniform outside a block, keyed by name
+pub(crate) unsafe fn uniform_slots(program: GLuint) -> HashMap<String, UniformSlot> {
+    let mut slots = HashMap::new();
+    for uniform in introspection::active_uniforms(program) {
+        let Some(location) = uniform.location else {
+            continue;
+        };
+        let slot = UniformSlot {
+            location,
+            gl_type: uniform.gl_type,
+        };
+
+        // Arrays are reported as "name[0]", allow setting the first element by "name" too
+        if let Some(base) = uniform.name.strip_suffix("[0]") {
+            slots.insert(base.to_string(), slot);
+        }
+        slots.insert(uniform.name, slot);
+    }
+
+    slots
+}
+
+pub(crate) fn is_sampler(gl_type: GLenum) -> bool {
+    matches!(
+        gl_type,
+        gl::SAMPLER_1D
+            | gl::SAMPLER_2D
+            | gl::SAMPLER_3D
+            | gl::SAMPLER_CUBE
+            | gl::SAMPLER_1D_SHADOW
+            | gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_1D_ARRAY
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_1D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_2D_MULTISAMPLE
+            | gl::SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::SAMPLER_BUFFER
+            | gl::SAMPLER_2D_RECT
+            | gl::SAMPLER_2D_RECT_SHADOW
+            | gl::INT_SAMPLER_1D
+            | gl::INT_SAMPLER_2D
+            | gl::INT_SAMPLER_3D
+            | gl::INT_SAMPLER_CUBE
+            | gl::INT_SAMPLER_1D_ARRAY
+            | gl::INT_SAMPLER_2D_ARRAY
+            | gl::INT_SAMPLER_2D_MULTISAMPLE
+            | gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::INT_SAMPLER_BUFFER
+            | gl::INT_SAMPLER_2D_RECT
+            | gl::UNSIGNED_INT_SAMPLER_1D
+            | gl::UNSIGNED_INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_3D
+            | gl::UNSIGNED_INT_SAMPLER_CUBE
+            | gl::UNSIGNED_INT_SAMPLER_1D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE
+            | gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY
+            | gl::UNSIGNED_INT_SAMPLER_BUFFER
+            | gl::UNSIGNED_INT_SAMPLER_2D_RECT
+    )
+}
+
+impl Uniform for f32 {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1f(location, *self);
+    }
+}
+
+impl Uniform for i32 {
+    // Samplers are plain ints as far as GL is concerned
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::INT || gl_type == gl::BOOL || is_sampler(gl_type)
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self);
+    }
+}
+
+impl Uniform for u32 {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::UNSIGNED_INT
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1ui(location, *self);
+    }
+}
+
+impl Uniform for bool {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::BOOL
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, *self as GLint);
+    }
+}
+
+impl Uniform for TextureUnit {
+    fn accepts(gl_type: GLenum) -> bool {
+        is_sampler(gl_type)
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform1i(location, self.0 as GLint);
+    }
+}
+
+impl Uniform for [f32; 2] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC2
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform2fv(location, 1, self.as_ptr());
+    }
+}
+
+impl Uniform for [f32; 3] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC3
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform3fv(location, 1, self.as_ptr());
+    }
+}
+
+impl Uniform for [f32; 4] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_VEC4
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform4fv(location, 1, self.as_ptr());
+    }
+}
+
+impl Uniform for [i32; 2] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::INT_VEC2
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform2iv(location, 1, self.as_ptr());
+    }
+}
+
+impl Uniform for [i32; 3] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::INT_VEC3
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform3iv(location, 1, self.as_ptr());
+    }
+}
+
+impl Uniform for [i32; 4] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::INT_VEC4
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::Uniform4iv(location, 1, self.as_ptr());
+    }
+}
+
+// Matrices are column major, matching GLSL
+impl Uniform for [[f32; 3]; 3] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_MAT3
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::UniformMatrix3fv(location, 1, gl::FALSE, self.as_ptr() as *const GLfloat);
+    }
+}
+
+impl Uniform for [[f32; 4]; 4] {
+    fn accepts(gl_type: GLenum) -> bool {
+        gl_type == gl::FLOAT_MAT4
+    }
+
+    unsafe fn set(&self, location: GLint) {
+        gl::UniformMatrix4fv(location, 1, gl::FALSE, self.as_ptr() as *const GLfloat);
+    }
+}
@@ -0,0 +1,312 @@
+use crate::buffer::Buffer;
+use crate::stream::StreamBuffer;
+use bytemuck::Pod;
+use gl::types::*;
+
+/// A type a single vertex attribute can be read from.
+pub trait AttributeFormat {
+    const COMPONENTS: GLint;
+    const GL_TYPE: GLenum;
+}
+
+macro_rules! impl_attribute_format {
+    ($($type:ty => $gl_type:expr),*) => {$(
+        impl AttributeFormat for $type {
+            const COMPONENTS: GLint = 1;
+            const GL_TYPE: GLenum = $gl_type;
+        }
+
+        impl AttributeFormat for [$type; 2] {
+            const COMPONENTS: GLint = 2;
+            const GL_TYPE: GLenum = $gl_type;
+        }
+
+        impl AttributeFormat for [$type; 3] {
+            const COMPONENTS: GLint = 3;
+            const GL_TYPE: GLenum = $gl_type;
+        }
+
+        impl AttributeFormat for [$type; 4] {
+            const COMPONENTS: GLint = 4;
+            const GL_TYPE: GLenum = $gl_type;
+        }
+    )*};
+}
+
+impl_attribute_format!(
+    f32 => gl::FLOAT,
+    i32 => gl::INT,
+    u32 => gl::UNSIGNED_INT,
+    i16 => gl::SHORT,
+    u16 => gl::UNSIGNED_SHORT,
+    i8 => gl::BYTE,
+    u8 => gl::UNSIGNED_BYTE
+);
+
+/// How the shader sees an attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    Float,
+    /// Integers scaled to 0..1, or -1..1 for signed types
+    Normalized,
+    /// Integers read as `int` or `uint`, set up with `glVertexAttribIPointer`
+    Integer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location: GLuint,
+    pub components: GLint,
+    pub gl_type: GLenum,
+    pub kind: AttributeKind,
+    /// Byte offset within the vertex
+    pub offset: usize,
+}
+
+impl VertexAttribute {
+    /// Integer types are read as integers unless `normalized` is set, float types are always
+    /// read as floats and ignore it.
+    pub fn new<T: AttributeFormat>(
+        location: GLuint,
+        normalized: bool,
+        offset: usize,
+    ) -> VertexAttribute {
+        let kind = match T::GL_TYPE {
+            gl::FLOAT => AttributeKind::Float,
+            _ if normalized => AttributeKind::Normalized,
+            _ => AttributeKind::Integer,
+        };
+
+        VertexAttribute {
+            location,
+            components: T::COMPONENTS,
+            gl_type: T::GL_TYPE,
+            kind,
+            offset,
+        }
+    }
+
+    /// The type the shader input should be declared with, such as `gl::FLOAT_VEC2`.
+    pub fn glsl_type(&self) -> GLenum {
+        let types = match self.kind {
+            AttributeKind::Float | AttributeKind::Normalized => {
+                [gl::FLOAT, gl::FLOAT_VEC2, gl::FLOAT_VEC3, gl::FLOAT_VEC4]
+            }
+            AttributeKind::Integer => match self.gl_type {
+                gl::UNSIGNED_INT | gl::UNSIGNED_SHORT | gl::UNSIGNED_BYTE => [
+                    gl::UNSIGNED_INT,
+                    gl::UNSIGNED_INT_VEC2,
+                    gl::UNSIGNED_INT_VEC3,
+                    gl::UNSIGNED_INT_VEC4,
+                ],
+                _ => [gl::INT, gl::INT_VEC2, gl::INT_VEC3, gl::INT_VEC4],
+            },
+        };
+        types[self.components as usize - 1]
+    }
+}
+
+/// A vertex type that knows its attribute layout, use `#[derive(Vertex)]` on a
+/// `#[repr(C)]` struct.
+pub trait Vertex: Pod {
+    fn attributes() -> Vec<VertexAttribute>;
+}
+
+/// A type usable in an index buffer.
+pub trait Index: Pod {
+    const GL_TYPE: GLenum;
+}
+
+impl Index for u8 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_BYTE;
+}
+
+impl Index for u16 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_SHORT;
+}
+
+impl Index for u32 {
+    const GL_TYPE: GLenum = gl::UNSIGNED_INT;
+}
+
+/// Owns a vertex array object, which is deleted when dropped.
+///
+/// The buffers it reads from are not owned, keep them alive for as long as it is drawn.
+pub struct VertexArray {
+    vao: GLuint,
+    index_type: Option<GLenum>,
+}
+
+impl VertexArray {
+    pub fn builder() -> VertexArrayBuilder {
+        VertexArrayBuilder::default()
+    }
+
+    pub fn get(&self) -> GLuint {
+        self.vao
+    }
+
+    /// The type of the index buffer, if there is one.
+    pub fn index_type(&self) -> Option<GLenum> {
+        self.index_type
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+        }
+    }
+
+    /// Adds the attributes of `T` read from `buffer` after the fact, typically per-instance
+    /// data with a `divisor` of 1. The vertex array bound before stays bound.
+    pub fn add_buffer<T: Vertex>(&self, buffer: &Buffer<T>, divisor: GLuint) {
+        unsafe {
+            let previous = Bindings::save();
+            gl::BindVertexArray(self.vao);
+            set_attributes(&VertexBinding::new::<T>(buffer.get(), divisor));
+            previous.restore();
+        }
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+struct VertexBinding {
+    buffer: GLuint,
+    stride: GLsizei,
+    attributes: Vec<VertexAttribute>,
+    divisor: GLuint,
+}
+
+impl VertexBinding {
+    fn new<T: Vertex>(buffer: GLuint, divisor: GLuint) -> VertexBinding {
+        VertexBinding {
+            buffer,
+            stride: size_of::<T>() as GLsizei,
+            attributes: T::attributes(),
+            divisor,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct VertexArrayBuilder {
+    bindings: Vec<VertexBinding>,
+    index_buffer: Option<(GLuint, GLenum)>,
+}
+
+impl VertexArrayBuilder {
+    /// Reads the attributes of `T` from `buffer`, one element per vertex.
+    pub fn vertex_buffer<T: Vertex>(self, buffer: &Buffer<T>) -> VertexArrayBuilder {
+        self.instance_buffer(buffer, 0)
+    }
+
+    /// Reads the attributes of `T` from `buffer`, advancing one element every `divisor`
+    /// instances. A divisor of 0 advances per vertex instead.
+    pub fn instance_buffer<T: Vertex>(
+        mut self,
+        buffer: &Buffer<T>,
+        divisor: GLuint,
+    ) -> VertexArrayBuilder {
+        self.bindings
+            .push(VertexBinding::new::<T>(buffer.get(), divisor));
+        self
+    }
+
+    /// Reads the attributes of `T` from a stream buffer, one element per vertex. Draw from
+    /// the range `StreamBuffer::write` returns.
+    pub fn stream_buffer<T: Vertex>(mut self, buffer: &StreamBuffer<T>) -> VertexArrayBuilder {
+        self.bindings.push(VertexBinding::new::<T>(buffer.get(), 0));
+        self
+    }
+
+    pub fn index_buffer<I: Index>(mut self, buffer: &Buffer<I>) -> VertexArrayBuilder {
+        self.index_buffer = Some((buffer.get(), I::GL_TYPE));
+        self
+    }
+
+    /// Creates the vertex array, leaving the one bound before bound.
+    pub fn build(self) -> VertexArray {
+        let mut vao = 0;
+        unsafe {
+            let previous = Bindings::save();
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            for binding in &self.bindings {
+                set_attributes(binding);
+            }
+
+            // The element buffer binding is part of the vertex array state
+            if let Some((buffer, _)) = self.index_buffer {
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer);
+            }
+
+            previous.restore();
+        }
+
+        VertexArray {
+            vao,
+            index_type: self.index_buffer.map(|(_, index_type)| index_type),
+        }
+    }
+}
+
+// The vertex array and array buffer bound before one is edited, so the caller's state
+// survives it
+struct Bindings {
+    vao: GLuint,
+    array_buffer: GLuint,
+}
+
+impl Bindings {
+    unsafe fn save() -> Bindings {
+        let (mut vao, mut array_buffer) = (0, 0);
+        gl::GetIntegerv(gl::VERTEX_ARRAY_BINDING, &mut vao);
+        gl::GetIntegerv(gl::ARRAY_BUFFER_BINDING, &mut array_buffer);
+        Bindings {
+            vao: vao as GLuint,
+            array_buffer: array_buffer as GLuint,
+        }
+    }
+
+    unsafe fn restore(&self) {
+        gl::BindVertexArray(self.vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.array_buffer);
+    }
+}
+
+// Point the attributes of one buffer at it, with the vertex array to change bound
+unsafe fn set_attributes(binding: &VertexBinding) {
+    gl::BindBuffer(gl::ARRAY_BUFFER, binding.buffer);
+    for attribute in &binding.attributes {
+        let offset = attribute.offset as *const _;
+        match attribute.kind {
+            AttributeKind::Integer => gl::VertexAttribIPointer(
+                attribute.location,
+                attribute.components,
+                attribute.gl_type,
+                binding.stride,
+                offset,
+            ),
+            kind => gl::VertexAttribPointer(
+                attribute.location,
+                attribute.components,
+                attribute.gl_type,
+                (kind == AttributeKind::Normalized) as GLboolean,
+                binding.stride,
+                offset,
+            ),
+        }
+        gl::EnableVertexAttribArray(attribute.location);
+        if binding.divisor != 0 {
+            gl::VertexAttribDivisor(attribute.location, binding.divisor);
+        }
+    }
+}
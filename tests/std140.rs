@@ -0,0 +1,118 @@
+// Offsets of a derived std140 struct, checked against the rules of the GLSL spec
+
+use opengl::std140::to_bytes;
+use opengl::Std140;
+
+#[derive(Std140)]
+struct Light {
+    position: [f32; 3],
+    intensity: f32,
+}
+
+#[derive(Std140)]
+struct Scene {
+    time: f32,
+    direction: [f32; 3],
+    enabled: bool,
+    offset: [f32; 2],
+    rotation: [[f32; 3]; 3],
+    weights: [f32; 5],
+    light: Light,
+    lights: [Light; 2],
+    count: u32,
+}
+
+fn scene() -> Scene {
+    let light = |value: f32| Light {
+        position: [value, value + 1.0, value + 2.0],
+        intensity: value + 3.0,
+    };
+    Scene {
+        time: 1.5,
+        direction: [2.0, 3.0, 4.0],
+        enabled: true,
+        offset: [5.0, 6.0],
+        rotation: [[7.0, 8.0, 9.0], [10.0, 11.0, 12.0], [13.0, 14.0, 15.0]],
+        weights: [16.0, 17.0, 18.0, 19.0, 20.0],
+        light: light(21.0),
+        lights: [light(25.0), light(29.0)],
+        count: 33,
+    }
+}
+
+fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn struct_layout() {
+    assert_eq!((Light::ALIGN, Light::SIZE), (16, 16));
+    assert_eq!((Scene::ALIGN, Scene::SIZE), (16, 240));
+}
+
+#[test]
+fn member_offsets() {
+    let mut members = Vec::new();
+    Scene::std140_members("", 0, &mut members);
+    let members: Vec<(&str, usize)> = members
+        .iter()
+        .map(|(name, offset)| (name.as_str(), *offset))
+        .collect();
+
+    assert_eq!(
+        members,
+        [
+            ("time", 0),
+            // vec3 aligns to 16 but the bool packs into its last four bytes
+            ("direction", 16),
+            ("enabled", 28),
+            ("offset", 32),
+            // mat3 columns and float array elements each take a vec4 slot
+            ("rotation", 48),
+            ("rotation[0]", 48),
+            ("rotation[1]", 64),
+            ("rotation[2]", 80),
+            ("weights", 96),
+            ("weights[0]", 96),
+            ("weights[1]", 112),
+            ("weights[2]", 128),
+            ("weights[3]", 144),
+            ("weights[4]", 160),
+            ("light.position", 176),
+            ("light.intensity", 188),
+            ("lights", 192),
+            ("lights[0].position", 192),
+            ("lights[0].intensity", 204),
+            ("lights[1].position", 208),
+            ("lights[1].intensity", 220),
+            ("count", 224),
+        ]
+    );
+}
+
+#[test]
+fn writes_at_the_offsets() {
+    let bytes = to_bytes(&scene());
+    assert_eq!(bytes.len(), 240);
+
+    assert_eq!(f32_at(&bytes, 0), 1.5);
+    assert_eq!(f32_at(&bytes, 4), 0.0);
+    assert_eq!(f32_at(&bytes, 16), 2.0);
+    assert_eq!(f32_at(&bytes, 24), 4.0);
+    assert_eq!(u32_at(&bytes, 28), 1);
+    assert_eq!(f32_at(&bytes, 36), 6.0);
+    assert_eq!(f32_at(&bytes, 64), 10.0);
+    assert_eq!(f32_at(&bytes, 88), 15.0);
+    assert_eq!(f32_at(&bytes, 92), 0.0);
+    assert_eq!(f32_at(&bytes, 112), 17.0);
+    assert_eq!(f32_at(&bytes, 160), 20.0);
+    assert_eq!(f32_at(&bytes, 176), 21.0);
+    assert_eq!(f32_at(&bytes, 188), 24.0);
+    assert_eq!(f32_at(&bytes, 208), 29.0);
+    assert_eq!(f32_at(&bytes, 220), 32.0);
+    assert_eq!(u32_at(&bytes, 224), 33);
+}
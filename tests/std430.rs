@@ -0,0 +1,99 @@
+// Offsets of a derived std430 struct, checked against the rules of the GLSL spec
+
+use opengl::Std430;
+
+#[derive(Debug, Clone, Copy, PartialEq, Std430)]
+struct Light {
+    position: [f32; 3],
+    intensity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Std430)]
+struct Scene {
+    time: f32,
+    direction: [f32; 3],
+    enabled: bool,
+    offset: [f32; 2],
+    rotation: [[f32; 3]; 3],
+    weights: [f32; 5],
+    light: Light,
+    lights: [Light; 2],
+    count: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Std430)]
+struct Particle {
+    velocity: [f32; 2],
+    life: f32,
+}
+
+fn scene() -> Scene {
+    let light = |value: f32| Light {
+        position: [value, value + 1.0, value + 2.0],
+        intensity: value + 3.0,
+    };
+    Scene {
+        time: 1.5,
+        direction: [2.0, 3.0, 4.0],
+        enabled: true,
+        offset: [5.0, 6.0],
+        rotation: [[7.0, 8.0, 9.0], [10.0, 11.0, 12.0], [13.0, 14.0, 15.0]],
+        weights: [16.0, 17.0, 18.0, 19.0, 20.0],
+        light: light(21.0),
+        lights: [light(25.0), light(29.0)],
+        count: 33,
+    }
+}
+
+fn f32_at(bytes: &[u8], offset: usize) -> f32 {
+    f32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[test]
+fn struct_layout() {
+    assert_eq!((Light::ALIGN, Light::SIZE), (16, 16));
+    assert_eq!((Scene::ALIGN, Scene::SIZE), (16, 192));
+    // Only as aligned as the vec2, and padded to a multiple of that
+    assert_eq!((Particle::ALIGN, Particle::SIZE), (8, 16));
+}
+
+#[test]
+fn writes_at_the_offsets() {
+    let mut bytes = vec![0; Scene::SIZE];
+    scene().write_std430(&mut bytes);
+
+    assert_eq!(f32_at(&bytes, 0), 1.5);
+    assert_eq!(f32_at(&bytes, 16), 2.0);
+    assert_eq!(u32::read_std430(&bytes[28..]), 1);
+    assert_eq!(f32_at(&bytes, 32), 5.0);
+    // mat3 columns still take a vec4 slot
+    assert_eq!(f32_at(&bytes, 48), 7.0);
+    assert_eq!(f32_at(&bytes, 64), 10.0);
+    assert_eq!(f32_at(&bytes, 92), 0.0);
+    // but float arrays are tightly packed, unlike std140
+    assert_eq!(f32_at(&bytes, 96), 16.0);
+    assert_eq!(f32_at(&bytes, 100), 17.0);
+    assert_eq!(f32_at(&bytes, 112), 20.0);
+    assert_eq!(f32_at(&bytes, 128), 21.0);
+    assert_eq!(f32_at(&bytes, 140), 24.0);
+    assert_eq!(f32_at(&bytes, 144), 25.0);
+    assert_eq!(f32_at(&bytes, 160), 29.0);
+    assert_eq!(u32::read_std430(&bytes[176..]), 33);
+}
+
+#[test]
+fn reads_back_what_it_wrote() {
+    let scene = scene();
+    let mut bytes = vec![0; Scene::SIZE];
+    scene.write_std430(&mut bytes);
+    assert_eq!(Scene::read_std430(&bytes), scene);
+
+    let particle = Particle {
+        velocity: [1.0, -2.0],
+        life: 0.5,
+    };
+    let mut bytes = vec![0; Particle::SIZE];
+    particle.write_std430(&mut bytes);
+    assert_eq!(f32_at(&bytes, 8), 0.5);
+    assert_eq!(Particle::read_std430(&bytes), particle);
+}
@@ -0,0 +1,78 @@
+// Attribute layouts of a derived vertex, checked against the struct's own field offsets
+
+use opengl::{AttributeKind, Pod, Vertex, VertexAttribute, Zeroable};
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable, Vertex)]
+struct Particle {
+    position: [f32; 3],
+    #[vertex(normalized)]
+    color: [u8; 4],
+    size: f32,
+    #[vertex(normalized)]
+    normal: [i16; 4],
+    ids: [i32; 2],
+    #[vertex(location = 7)]
+    bones: [u16; 2],
+}
+
+fn attribute(
+    location: u32,
+    components: i32,
+    gl_type: u32,
+    kind: AttributeKind,
+    offset: usize,
+) -> VertexAttribute {
+    VertexAttribute {
+        location,
+        components,
+        gl_type,
+        kind,
+        offset,
+    }
+}
+
+#[test]
+fn stride_and_offsets() {
+    // The buffer stride is the size of the struct, which has no padding here
+    assert_eq!(size_of::<Particle>(), 40);
+    let offsets: Vec<usize> = Particle::attributes()
+        .iter()
+        .map(|attribute| attribute.offset)
+        .collect();
+    assert_eq!(offsets, [0, 12, 16, 20, 28, 36]);
+}
+
+#[test]
+fn attributes() {
+    assert_eq!(
+        Particle::attributes(),
+        [
+            attribute(0, 3, gl::FLOAT, AttributeKind::Float, 0),
+            attribute(1, 4, gl::UNSIGNED_BYTE, AttributeKind::Normalized, 12),
+            attribute(2, 1, gl::FLOAT, AttributeKind::Float, 16),
+            attribute(3, 4, gl::SHORT, AttributeKind::Normalized, 20),
+            attribute(4, 2, gl::INT, AttributeKind::Integer, 28),
+            attribute(7, 2, gl::UNSIGNED_SHORT, AttributeKind::Integer, 36),
+        ]
+    );
+}
+
+#[test]
+fn shader_input_types() {
+    let types: Vec<u32> = Particle::attributes()
+        .iter()
+        .map(VertexAttribute::glsl_type)
+        .collect();
+    assert_eq!(
+        types,
+        [
+            gl::FLOAT_VEC3,
+            gl::FLOAT_VEC4,
+            gl::FLOAT,
+            gl::FLOAT_VEC4,
+            gl::INT_VEC2,
+            gl::UNSIGNED_INT_VEC2,
+        ]
+    );
+}